@@ -1,4 +1,26 @@
-use clap::Parser;
+use crate::core::history::{ReportFormat, ReportGroup};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+/// How the statusline is written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// ANSI-colored text for Claude Code
+    #[default]
+    Ansi,
+    /// Segment data and resolved colors as JSON
+    Json,
+    /// tmux status-line markup (`#[fg=...,bg=...]`)
+    Tmux,
+    /// zsh prompt escapes
+    Zsh,
+    /// bash PS1 escapes
+    Bash,
+    /// i3bar protocol blocks
+    I3bar,
+    /// waybar custom module JSON
+    Waybar,
+}
 
 #[derive(Parser, Debug)]
 #[command(name = "micusubcodeline")]
@@ -24,6 +46,10 @@ pub struct Cli {
     #[arg(long = "check")]
     pub check: bool,
 
+    /// Print a JSON Schema of the config file for editor completion
+    #[arg(long = "schema")]
+    pub schema: bool,
+
     /// Check for updates
     #[arg(short = 'u', long = "update")]
     pub update: bool,
@@ -32,9 +58,55 @@ pub struct Cli {
     #[arg(long = "init-subscription")]
     pub init_subscription: bool,
 
+    /// Refresh the subscription cache (spawned in the background by the statusline)
+    #[arg(long = "refresh-subscription", hide = true)]
+    pub refresh_subscription: bool,
+
+    /// Endpoints for --refresh-subscription, as resolved by the statusline
+    #[arg(long = "subscription-endpoint", hide = true)]
+    pub subscription_endpoints: Vec<String>,
+
+    /// Refresh an expired OAuth token (spawned in the background by the statusline)
+    #[arg(long = "refresh-oauth", hide = true)]
+    pub refresh_oauth: bool,
+
+    /// Token endpoint for --refresh-oauth
+    #[arg(long = "oauth-token-url", hide = true)]
+    pub oauth_token_url: Option<String>,
+
+    /// Use this directory for config, themes and caches instead of the
+    /// default location
+    #[arg(long = "config-dir", global = true)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Output format for the statusline
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Ansi)]
+    pub format: OutputFormat,
+
     /// Patch Claude Code cli.js to disable context warnings
     #[arg(long = "patch")]
     pub patch: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Summarize recorded session cost and usage
+    Report {
+        /// Group rows by day, project directory or model
+        #[arg(long = "by", value_enum, default_value_t = ReportGroup::Day)]
+        by: ReportGroup,
+
+        /// Report format
+        #[arg(long = "format", value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+
+        /// Only include the last N days
+        #[arg(long = "days")]
+        days: Option<u64>,
+    },
 }
 
 impl Cli {
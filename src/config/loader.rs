@@ -1,4 +1,7 @@
-use super::types::Config;
+use super::migration::{self, MigrationReport};
+use super::paths;
+use super::project::ProjectConfig;
+use super::types::{ColorRule, Config};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -9,6 +12,8 @@ pub enum InitResult {
     Created(PathBuf),
     /// Config already existed at the given path
     AlreadyExists(PathBuf),
+    /// Config was created at `path` from the CCometixLine config at `from`
+    Imported { path: PathBuf, from: PathBuf },
 }
 
 pub struct ConfigLoader;
@@ -20,7 +25,7 @@ impl ConfigLoader {
 
     pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Config, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        let (config, _) = migration::load_str(&content)?;
         Ok(config)
     }
 
@@ -63,15 +68,41 @@ impl ConfigLoader {
         Ok(())
     }
 
-    /// Get the themes directory path (~/.claude/micusubcodeline/themes/)
+    /// Get the themes directory path (`themes/` in the config directory)
     pub fn get_themes_path() -> PathBuf {
-        if let Some(home) = dirs::home_dir() {
-            home.join(".claude").join("micusubcodeline").join("themes")
+        if let Some(config_dir) = paths::config_dir() {
+            config_dir.join("themes")
         } else {
             PathBuf::from(".claude/micusubcodeline/themes")
         }
     }
 
+    /// Upgrade every theme file in `themes/` to the current version, backing
+    /// up the outdated ones (the `--check` counterpart of the in-memory
+    /// upgrade done when a theme is loaded). Returns each file with its
+    /// migration report, or why it could not be upgraded.
+    pub fn upgrade_themes() -> Vec<(PathBuf, Result<MigrationReport, String>)> {
+        let Ok(entries) = fs::read_dir(Self::get_themes_path()) else {
+            return Vec::new();
+        };
+        let mut theme_paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        theme_paths.sort();
+
+        theme_paths
+            .into_iter()
+            .map(|path| {
+                let result = migration::load_file(&path, true)
+                    .map(|(_, report)| report)
+                    .map_err(|e| e.to_string());
+                (path, result)
+            })
+            .collect()
+    }
+
     /// Ensure themes directory exists and has built-in themes (silent mode)
     pub fn ensure_themes_exist() {
         // Silently ensure themes exist without printing output
@@ -112,22 +143,66 @@ impl ConfigLoader {
 }
 
 impl Config {
-    /// Load configuration from default location
+    /// Load configuration from default location. An outdated file is
+    /// upgraded in memory only; `--check` writes the upgrade and `--print`
+    /// / `--check` report migration notes and unknown keys.
     pub fn load() -> Result<Config, Box<dyn std::error::Error>> {
+        let (config, _) = Self::load_with_report(false)?;
+        Ok(config)
+    }
+
+    /// Load configuration and report what migration did. With `rewrite`, an
+    /// outdated config file is backed up and upgraded on disk. Without a
+    /// config file, the upstream CCometixLine config is read if there is one.
+    pub fn load_with_report(
+        rewrite: bool,
+    ) -> Result<(Config, MigrationReport), Box<dyn std::error::Error>> {
         // Ensure themes directory exists and has built-in themes
         ConfigLoader::ensure_themes_exist();
 
         let config_path = Self::get_config_path();
+        if config_path.exists() {
+            return migration::load_file(&config_path, rewrite);
+        }
 
-        if !config_path.exists() {
-            return Ok(Config::default());
+        // Never rewrite the other tool's file
+        if let Some(upstream_path) = paths::upstream_config_path().filter(|p| p.exists()) {
+            let (config, mut report) = migration::load_file(&upstream_path, false)?;
+            report.warnings.insert(
+                0,
+                format!(
+                    "using CCometixLine config {}; run --init to copy it",
+                    upstream_path.display()
+                ),
+            );
+            return Ok((config, report));
         }
 
-        let content = fs::read_to_string(config_path)?;
-        let config: Config = toml::from_str(&content)?;
+        Ok((
+            Config::default(),
+            MigrationReport {
+                from_version: migration::CONFIG_VERSION,
+                ..Default::default()
+            },
+        ))
+    }
+
+    /// Load configuration with the project's `.micusubcodeline.toml` (if
+    /// any) layered on top
+    pub fn load_for_workspace(current_dir: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = Self::load()?;
+        config.apply_project(current_dir);
         Ok(config)
     }
 
+    /// Layer the project config found from `current_dir` over this one.
+    /// Returns the project file that was applied.
+    pub fn apply_project(&mut self, current_dir: &str) -> Option<PathBuf> {
+        let (path, project) = ProjectConfig::for_workspace(current_dir)?;
+        project.apply(self);
+        Some(path)
+    }
+
     /// Save configuration to default location
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
         let config_path = Self::get_config_path();
@@ -142,12 +217,11 @@ impl Config {
         Ok(())
     }
 
-    /// Get the default config file path (~/.claude/micusubcodeline/config.toml)
-    fn get_config_path() -> PathBuf {
-        if let Some(home) = dirs::home_dir() {
-            home.join(".claude")
-                .join("micusubcodeline")
-                .join("config.toml")
+    /// Get the config file path (`config.toml` in the config directory, see
+    /// `config::paths`)
+    pub fn get_config_path() -> PathBuf {
+        if let Some(config_dir) = paths::config_dir() {
+            config_dir.join("config.toml")
         } else {
             PathBuf::from(".claude/micusubcodeline/config.toml")
         }
@@ -165,8 +239,17 @@ impl Config {
         // Initialize themes directory and built-in themes
         ConfigLoader::init_themes()?;
 
-        // Create default config if it doesn't exist
+        // Create default config if it doesn't exist, starting from the
+        // CCometixLine config when there is one
         if !config_path.exists() {
+            if let Some(upstream_path) = paths::upstream_config_path().filter(|p| p.exists()) {
+                let (config, _) = migration::load_file(&upstream_path, false)?;
+                config.save()?;
+                return Ok(InitResult::Imported {
+                    path: config_path,
+                    from: upstream_path,
+                });
+            }
             let default_config = Config::default();
             default_config.save()?;
             Ok(InitResult::Created(config_path))
@@ -182,12 +265,40 @@ impl Config {
             return Err("No segments configured".into());
         }
 
-        // Validate segment IDs are unique
+        // Validate segment IDs are unique; custom segments may repeat but
+        // must have unique names and a command to run
         let mut seen_ids = std::collections::HashSet::new();
+        let mut seen_custom_names = std::collections::HashSet::new();
         for segment in &self.segments {
-            if !seen_ids.insert(segment.id) {
+            if let Some(name) = segment.custom_name() {
+                if !seen_custom_names.insert(name) {
+                    return Err(format!("Duplicate custom segment name: {}", name).into());
+                }
+                let has_command = segment
+                    .options
+                    .get("command")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|c| !c.trim().is_empty());
+                if !has_command {
+                    return Err(format!("Custom segment '{}' has no command", name).into());
+                }
+            } else if !seen_ids.insert(segment.id) {
                 return Err(format!("Duplicate segment ID: {:?}", segment.id).into());
             }
+
+            // Every color rule must deserialize and have a parsable condition
+            if let Some(rules) = segment.options.get("color_rules") {
+                let rules: Vec<ColorRule> = serde_json::from_value(rules.clone()).map_err(|e| {
+                    format!("Invalid color_rules for {:?} segment: {}", segment.id, e)
+                })?;
+                if let Some(rule) = rules.iter().find(|r| r.parse_condition().is_none()) {
+                    return Err(format!(
+                        "Invalid color rule condition for {:?} segment: '{}'",
+                        segment.id, rule.when
+                    )
+                    .into());
+                }
+            }
         }
 
         Ok(())
@@ -0,0 +1,466 @@
+//! Versioned config files.
+//!
+//! Config and theme files carry a `version` key. Older files are upgraded
+//! step by step on load and, for this tool's own files, rewritten in place
+//! after a backup:
+//!
+//! - version 0: the original flat layout, `[segments]` as a table of
+//!   booleans (see `SegmentsConfig`)
+//! - version 1: `[[segments]]` tables without a `version` key. Also the
+//!   format of CCometixLine, the tool this project was forked from.
+//! - version 2: adds `version`; every segment has all of its fields
+//!
+//! Keys the config doesn't know are dropped by deserialization; they are
+//! reported as warnings so a typo or a removed option doesn't vanish
+//! silently.
+
+use super::types::{Config, SegmentId};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+pub const CONFIG_VERSION: u32 = 2;
+
+/// What happened while loading a config or theme file
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// Version the file was written with
+    pub from_version: u32,
+    /// Copy of the file before it was rewritten
+    pub backup: Option<PathBuf>,
+    pub warnings: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn migrated(&self) -> bool {
+        self.from_version < CONFIG_VERSION
+    }
+}
+
+/// Load a config or theme file, upgrading it to the current version in
+/// memory. With `rewrite` (only on an explicit request such as `--check`),
+/// an outdated file is backed up and replaced by the upgraded config.
+pub fn load_file(
+    path: &Path,
+    rewrite: bool,
+) -> Result<(Config, MigrationReport), Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let (config, mut report) = load_str(&content)?;
+
+    if rewrite && report.migrated() {
+        let backup = backup_path(path, report.from_version);
+        fs::copy(path, &backup)?;
+        write_atomic(path, &toml::to_string_pretty(&config)?)?;
+        report.backup = Some(backup);
+    }
+    Ok((config, report))
+}
+
+/// Replace `path` through a temp file + rename, so a concurrent reader
+/// never sees a partial config
+fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let tmp_path = path.with_extension(format!("toml.{}", std::process::id()));
+    let result = fs::write(&tmp_path, content).and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Parse config TOML of any known version
+pub fn load_str(content: &str) -> Result<(Config, MigrationReport), Box<dyn std::error::Error>> {
+    let mut value: Value = toml::from_str(content)?;
+    let mut report = MigrationReport {
+        from_version: detect_version(&value),
+        ..Default::default()
+    };
+
+    if report.from_version > CONFIG_VERSION {
+        report.warnings.push(format!(
+            "config version {} is newer than this build supports ({}); unknown keys are ignored",
+            report.from_version, CONFIG_VERSION
+        ));
+    }
+    if report.from_version < 1 {
+        migrate_v0_to_v1(&mut value);
+    }
+    if report.from_version < 2 {
+        migrate_v1_to_v2(&mut value, &mut report.warnings);
+    }
+
+    let mut config: Config = value.clone().try_into()?;
+    config.version = CONFIG_VERSION;
+
+    let known = Value::try_from(&config)?;
+    collect_unknown_keys(&value, &known, "", &mut report.warnings);
+    Ok((config, report))
+}
+
+fn detect_version(value: &Value) -> u32 {
+    if let Some(version) = value.get("version").and_then(|v| v.as_integer()) {
+        return version.max(0) as u32;
+    }
+    match value.get("segments") {
+        Some(Value::Table(_)) => 0,
+        _ => 1,
+    }
+}
+
+/// `[segments]` booleans → `[[segments]]` tables from the theme preset.
+/// Segments the old file didn't list were not available then and start
+/// disabled.
+fn migrate_v0_to_v1(value: &mut Value) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    let theme = table
+        .get("theme")
+        .and_then(|v| v.as_str())
+        .unwrap_or("default")
+        .to_string();
+    let preset = crate::ui::themes::ThemePresets::get_builtin(&theme);
+
+    let enabled = match table.remove("segments") {
+        Some(Value::Table(enabled)) => enabled,
+        _ => Default::default(),
+    };
+    let segments = preset
+        .segments
+        .iter()
+        .filter_map(|segment| {
+            let mut segment = Value::try_from(segment).ok()?;
+            let id = segment.get("id")?.as_str()?.to_string();
+            let on = enabled.get(&id).and_then(|v| v.as_bool()).unwrap_or(false);
+            segment
+                .as_table_mut()?
+                .insert("enabled".to_string(), Value::Boolean(on));
+            Some(segment)
+        })
+        .collect();
+    table.insert("segments".to_string(), Value::Array(segments));
+}
+
+/// Fill in whatever the file leaves out from the theme preset, and drop
+/// segments this build doesn't have
+fn migrate_v1_to_v2(value: &mut Value, warnings: &mut Vec<String>) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    let theme = table
+        .entry("theme")
+        .or_insert_with(|| Value::String("default".to_string()))
+        .as_str()
+        .unwrap_or("default")
+        .to_string();
+    let preset = crate::ui::themes::ThemePresets::get_builtin(&theme);
+
+    if let Ok(Value::Table(preset_style)) = Value::try_from(&preset.style) {
+        let style = table
+            .entry("style")
+            .or_insert_with(|| Value::Table(Default::default()));
+        if let Some(style) = style.as_table_mut() {
+            for (key, default) in preset_style {
+                style.entry(key).or_insert(default);
+            }
+        }
+    }
+
+    if let Some(Value::Array(segments)) = table.get_mut("segments") {
+        segments.retain(|segment| {
+            let id = segment.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            let known = parse_segment_id(id).is_some();
+            if !known {
+                warnings.push(format!("dropped segment with unknown id '{}'", id));
+            }
+            known
+        });
+
+        for segment in segments.iter_mut() {
+            let Some(fields) = segment.as_table_mut() else {
+                continue;
+            };
+            let id = fields
+                .get("id")
+                .and_then(|v| v.as_str())
+                .and_then(parse_segment_id);
+            let defaults = preset
+                .segments
+                .iter()
+                .find(|s| Some(s.id) == id)
+                .and_then(|s| Value::try_from(s).ok());
+
+            fields.entry("enabled").or_insert(Value::Boolean(true));
+            let default = |key: &str| {
+                defaults
+                    .as_ref()
+                    .and_then(|d| d.get(key))
+                    .cloned()
+                    .unwrap_or_else(|| Value::Table(Default::default()))
+            };
+            for key in ["icon", "colors", "styles", "options"] {
+                fields.entry(key).or_insert_with(|| default(key));
+            }
+
+            // Required keys inside tables; the colors are optional
+            let required = [
+                ("icon", "plain", Value::String(String::new())),
+                ("icon", "nerd_font", Value::String(String::new())),
+                ("styles", "text_bold", Value::Boolean(false)),
+            ];
+            for (table_key, key, fallback) in required {
+                let value = default(table_key).get(key).cloned().unwrap_or(fallback);
+                if let Some(table) = fields.get_mut(table_key).and_then(|v| v.as_table_mut()) {
+                    table.entry(key).or_insert(value);
+                }
+            }
+        }
+    }
+
+    table.insert("version".to_string(), Value::Integer(CONFIG_VERSION as i64));
+}
+
+fn parse_segment_id(id: &str) -> Option<SegmentId> {
+    Value::String(id.to_string()).try_into().ok()
+}
+
+/// Keys present in `input` but not in the parsed config. Segment options
+/// are free-form and never reported.
+fn collect_unknown_keys(input: &Value, known: &Value, path: &str, warnings: &mut Vec<String>) {
+    match (input, known) {
+        (Value::Table(input), Value::Table(known)) => {
+            for (key, value) in input {
+                let key_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match known.get(key) {
+                    None => warnings.push(format!("ignored unknown key '{}'", key_path)),
+                    Some(_) if key == "options" => {}
+                    Some(known) => collect_unknown_keys(value, known, &key_path, warnings),
+                }
+            }
+        }
+        (Value::Array(input), Value::Array(known)) => {
+            for (i, (value, known)) in input.iter().zip(known).enumerate() {
+                collect_unknown_keys(value, known, &format!("{}[{}]", path, i), warnings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `config.toml` → `config.toml.v1.bak`, numbered if that exists already
+fn backup_path(path: &Path, from_version: u32) -> PathBuf {
+    let base = format!("{}.v{}.bak", path.display(), from_version);
+    let mut backup = PathBuf::from(&base);
+    let mut n = 1;
+    while backup.exists() {
+        backup = PathBuf::from(format!("{}.{}", base, n));
+        n += 1;
+    }
+    backup
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::themes::ThemePresets;
+
+    fn segment_ids(config: &Config) -> Vec<SegmentId> {
+        config.segments.iter().map(|s| s.id).collect()
+    }
+
+    #[test]
+    fn v0_segment_booleans_use_the_theme_preset() {
+        let (config, report) = load_str(
+            r#"
+theme = "nord"
+
+[segments]
+model = true
+git = false
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(report.from_version, 0);
+        assert!(report.migrated());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.theme, "nord");
+
+        let preset = ThemePresets::get_builtin("nord");
+        assert_eq!(segment_ids(&config), segment_ids(&preset));
+        assert_eq!(config.style.separator, preset.style.separator);
+        for segment in &config.segments {
+            // Only the segments switched on in the old file stay enabled
+            assert_eq!(segment.enabled, segment.id == SegmentId::Model);
+        }
+    }
+
+    /// The format CCometixLine writes: `[[segments]]` without `version`
+    #[test]
+    fn v1_without_version_fills_in_missing_fields() {
+        let (config, report) = load_str(
+            r#"
+theme = "default"
+
+[style]
+mode = "plain"
+separator = " | "
+
+[[segments]]
+id = "model"
+enabled = true
+
+[segments.icon]
+plain = "M"
+nerd_font = "m"
+
+[segments.colors]
+
+[segments.styles]
+text_bold = true
+
+[segments.options]
+
+[[segments]]
+id = "directory"
+enabled = false
+
+[[segments]]
+id = "weather"
+enabled = true
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(report.from_version, 1);
+        assert_eq!(
+            report.warnings,
+            vec!["dropped segment with unknown id 'weather'".to_string()]
+        );
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(
+            segment_ids(&config),
+            vec![SegmentId::Model, SegmentId::Directory]
+        );
+
+        let model = &config.segments[0];
+        assert!(model.enabled);
+        assert_eq!(model.icon.plain, "M");
+        assert!(model.styles.text_bold);
+
+        let directory = &config.segments[1];
+        let preset = ThemePresets::get_builtin("default");
+        let preset_directory = preset
+            .segments
+            .iter()
+            .find(|s| s.id == SegmentId::Directory)
+            .unwrap();
+        assert!(!directory.enabled);
+        assert_eq!(directory.icon.plain, preset_directory.icon.plain);
+        assert_eq!(directory.icon.nerd_font, preset_directory.icon.nerd_font);
+    }
+
+    #[test]
+    fn newer_version_is_read_with_a_warning() {
+        let current = toml::to_string_pretty(&ThemePresets::get_builtin("default")).unwrap();
+        let newer = current.replacen(
+            &format!("version = {}", CONFIG_VERSION),
+            &format!("version = {}", CONFIG_VERSION + 1),
+            1,
+        );
+        assert_ne!(newer, current);
+
+        let (config, report) = load_str(&newer).unwrap();
+        assert_eq!(report.from_version, CONFIG_VERSION + 1);
+        assert!(!report.migrated());
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("newer than this build supports"));
+        assert_eq!(config.version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_but_options_are_not() {
+        let (config, report) = load_str(
+            r#"
+version = 2
+theme = "default"
+colour = "blue"
+
+[style]
+mode = "plain"
+separator = " | "
+compact = true
+
+[[segments]]
+id = "model"
+enabled = true
+priority = 1
+
+[segments.icon]
+plain = "M"
+nerd_font = "m"
+
+[segments.colors]
+
+[segments.styles]
+text_bold = false
+italic = true
+
+[segments.options]
+anything = "goes"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(report.from_version, 2);
+        assert!(!report.migrated());
+        let mut warnings = report.warnings.clone();
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            vec![
+                "ignored unknown key 'colour'".to_string(),
+                "ignored unknown key 'segments[0].priority'".to_string(),
+                "ignored unknown key 'segments[0].styles.italic'".to_string(),
+                "ignored unknown key 'style.compact'".to_string(),
+            ]
+        );
+        assert_eq!(
+            config.segments[0].options.get("anything"),
+            Some(&serde_json::json!("goes"))
+        );
+    }
+
+    #[test]
+    fn load_file_rewrites_only_when_asked() {
+        let dir =
+            std::env::temp_dir().join(format!("micusubcodeline-migration-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let original = "theme = \"default\"\n\n[segments]\nmodel = true\n";
+        fs::write(&path, original).unwrap();
+
+        let (_, report) = load_file(&path, false).unwrap();
+        assert!(report.migrated());
+        assert!(report.backup.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+        let (config, report) = load_file(&path, true).unwrap();
+        let backup = report.backup.unwrap();
+        assert_eq!(backup, dir.join("config.toml.v0.bak"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+        let (rewritten, report) = load_file(&path, false).unwrap();
+        assert_eq!(report.from_version, CONFIG_VERSION);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(segment_ids(&rewritten), segment_ids(&config));
+
+        // An existing backup is never overwritten
+        assert_eq!(backup_path(&path, 0), dir.join("config.toml.v0.bak.1"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
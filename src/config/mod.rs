@@ -1,8 +1,13 @@
 pub mod defaults;
 pub mod loader;
+pub mod migration;
 pub mod models;
+pub mod paths;
+pub mod project;
+pub mod schema;
 pub mod types;
 
 pub use loader::{ConfigLoader, InitResult};
 pub use models::*;
+pub use project::ProjectConfig;
 pub use types::*;
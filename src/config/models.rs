@@ -1,18 +1,115 @@
+use super::paths;
+use super::project::ProjectConfig;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::sync::OnceLock;
+
+/// Context limit for models without a configured or derivable one
+const DEFAULT_CONTEXT_LIMIT: u32 = 200_000;
+
+/// Tokens below the context limit at which Claude Code auto-compacts
+/// (room kept for the response plus a safety buffer)
+const DEFAULT_COMPACT_RESERVE: u32 = 45_000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
-    #[serde(rename = "models")]
+    #[serde(rename = "models", default)]
     pub model_entries: Vec<ModelEntry>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelEntry {
+    /// Case-insensitive substring of the model id, or a regular expression
+    /// if `regex` is set
     pub pattern: String,
+    /// Name to show. For regex patterns, `{1}` inserts capture group 1 and
+    /// `{1|title}`, `{1|upper}`, `{1|lower}` change its case.
     pub display_name: String,
-    pub context_limit: u32,
+    /// Omitted: derived from the model id, or the default limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub regex: bool,
+    /// Tokens kept free below `context_limit` before auto-compact triggers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_reserve: Option<u32>,
+    /// Prices used to estimate cost when Claude Code doesn't report it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<ModelPricing>,
+    /// `pattern` compiled on first use; `None` inside if it is invalid
+    #[serde(skip)]
+    compiled: OnceLock<Option<Regex>>,
+}
+
+/// Token prices in USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    /// Cache creation; defaults to 1.25× input (5-minute cache)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    /// Cache hits; defaults to 0.1× input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+}
+
+impl ModelPricing {
+    /// Cost in USD of the given token counts
+    pub fn cost(
+        &self,
+        input_tokens: u64,
+        output_tokens: u64,
+        cache_write_tokens: u64,
+        cache_read_tokens: u64,
+    ) -> f64 {
+        let cache_write = self.cache_write.unwrap_or(self.input * 1.25);
+        let cache_read = self.cache_read.unwrap_or(self.input * 0.1);
+        (input_tokens as f64 * self.input
+            + output_tokens as f64 * self.output
+            + cache_write_tokens as f64 * cache_write
+            + cache_read_tokens as f64 * cache_read)
+            / 1_000_000.0
+    }
+}
+
+impl ModelEntry {
+    fn substring(pattern: &str, display_name: &str, context_limit: u32) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            display_name: display_name.to_string(),
+            context_limit: Some(context_limit),
+            regex: false,
+            compact_reserve: None,
+            pricing: None,
+            compiled: OnceLock::new(),
+        }
+    }
+
+    /// Display name for `model_id` if this entry matches it
+    fn match_id(&self, model_id: &str) -> Option<String> {
+        if !self.regex {
+            let matches = model_id
+                .to_lowercase()
+                .contains(&self.pattern.to_lowercase());
+            return matches.then(|| self.display_name.clone());
+        }
+
+        // Invalid expressions never match
+        let regex = self
+            .compiled
+            .get_or_init(|| {
+                RegexBuilder::new(&self.pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .ok()
+            })
+            .as_ref()?;
+        let caps = regex.captures(model_id)?;
+        Some(expand_captures(&self.display_name, &caps))
+    }
 }
 
 impl ModelConfig {
@@ -28,11 +125,8 @@ impl ModelConfig {
         let mut model_config = Self::default();
 
         // First, try to create default models.toml if it doesn't exist
-        if let Some(home_dir) = dirs::home_dir() {
-            let user_models_path = home_dir
-                .join(".claude")
-                .join("micusubcodeline")
-                .join("models.toml");
+        if let Some(config_dir) = paths::config_dir() {
+            let user_models_path = config_dir.join("models.toml");
             if !user_models_path.exists() {
                 let _ = Self::create_default_file(&user_models_path);
             }
@@ -40,11 +134,7 @@ impl ModelConfig {
 
         // Try loading from user config directory first, then local
         let config_paths = [
-            dirs::home_dir().map(|d| {
-                d.join(".claude")
-                    .join("micusubcodeline")
-                    .join("models.toml")
-            }),
+            paths::config_dir().map(|dir| dir.join("models.toml")),
             Some(Path::new("models.toml").to_path_buf()),
         ];
 
@@ -64,54 +154,75 @@ impl ModelConfig {
         model_config
     }
 
+    /// Load model configuration with the `[[models]]` entries of the
+    /// project's `.micusubcodeline.toml` taking priority
+    pub fn load_for_workspace(current_dir: &str) -> Self {
+        let mut model_config = Self::load();
+        if let Some((_, project)) = ProjectConfig::for_workspace(current_dir) {
+            let mut merged_entries = project.models;
+            merged_entries.extend(model_config.model_entries);
+            model_config.model_entries = merged_entries;
+        }
+        model_config
+    }
+
     /// Get context limit for a model based on ID pattern matching
-    /// Checks external config first, then falls back to built-in config
+    /// Checks external config first, then built-in config, then derives it
+    /// from Claude model ids
     pub fn get_context_limit(&self, model_id: &str) -> u32 {
-        let model_lower = model_id.to_lowercase();
-
-        // Check model entries
+        // The first matching entry that sets a limit wins
         for entry in &self.model_entries {
-            if model_lower.contains(&entry.pattern.to_lowercase()) {
-                return entry.context_limit;
+            if let (Some(limit), Some(_)) = (entry.context_limit, entry.match_id(model_id)) {
+                return limit;
             }
         }
 
-        200_000
+        parse_claude_model_id(model_id)
+            .map(|(_, limit)| limit)
+            .unwrap_or(DEFAULT_CONTEXT_LIMIT)
     }
 
     /// Get display name for a model based on ID pattern matching
-    /// Checks external config first, then falls back to built-in config
+    /// Checks external config first, then built-in config, then derives it
+    /// from Claude model ids
     /// Returns None if no match found (should use fallback display_name)
     pub fn get_display_name(&self, model_id: &str) -> Option<String> {
-        let model_lower = model_id.to_lowercase();
+        self.model_entries
+            .iter()
+            .find_map(|entry| entry.match_id(model_id))
+            .or_else(|| parse_claude_model_id(model_id).map(|(name, _)| name))
+    }
 
-        // Check model entries
-        for entry in &self.model_entries {
-            if model_lower.contains(&entry.pattern.to_lowercase()) {
-                return Some(entry.display_name.clone());
-            }
-        }
+    /// Get the auto-compact reserve for a model: the first matching entry
+    /// that sets one, else the default
+    pub fn get_compact_reserve(&self, model_id: &str) -> u32 {
+        self.model_entries
+            .iter()
+            .filter(|entry| entry.compact_reserve.is_some())
+            .find(|entry| entry.match_id(model_id).is_some())
+            .and_then(|entry| entry.compact_reserve)
+            .unwrap_or(DEFAULT_COMPACT_RESERVE)
+    }
 
-        None
+    /// Get token prices for a model: the first matching entry with a
+    /// `pricing` table, else Anthropic's list prices for Claude model ids
+    pub fn get_pricing(&self, model_id: &str) -> Option<ModelPricing> {
+        self.model_entries
+            .iter()
+            .filter(|entry| entry.pricing.is_some())
+            .find(|entry| entry.match_id(model_id).is_some())
+            .and_then(|entry| entry.pricing.clone())
+            .or_else(|| ClaudeModel::parse(model_id).map(|model| model.pricing()))
     }
 
     /// Create default model configuration file with minimal template
     pub fn create_default_file<P: AsRef<Path>>(path: P) -> Result<(), Box<dyn std::error::Error>> {
-        // Create a minimal template config (not the full fallback config)
-        let template_config = Self {
-            model_entries: vec![], // Empty - just provide the structure
-        };
-
-        let toml_content = toml::to_string_pretty(&template_config)?;
-
-        // Add comments and examples to the template
-        let template_content = format!(
-            "# MicuSubCodeLine Model Configuration\n\
+        // Only comments: an empty file means no custom models, and users can
+        // append [[models]] tables without conflicting with a `models = []`
+        let template_content = "# MicuSubCodeLine Model Configuration\n\
              # This file defines model display names and context limits for different LLM models\n\
              # File location: ~/.claude/micusubcodeline/models.toml\n\
              \n\
-             {}\n\
-             \n\
              # Model configurations\n\
              # Each [[models]] section defines a model pattern and its properties\n\
              # Order matters: first match wins, so put more specific patterns first\n\
@@ -120,9 +231,20 @@ impl ModelConfig {
              # [[models]]\n\
              # pattern = \"glm-4.5\"\n\
              # display_name = \"GLM-4.5\"\n\
-             # context_limit = 128000\n",
-            toml_content.trim()
-        );
+             # context_limit = 128000\n\
+             # # USD per million tokens, used to estimate cost when it isn't reported\n\
+             # pricing = { input = 0.6, output = 2.2, cache_write = 0.6, cache_read = 0.11 }\n\
+             # # Tokens kept free below context_limit before auto-compact (default 45000)\n\
+             # compact_reserve = 30000\n\
+             \n\
+             # Regex patterns can reuse capture groups in the name\n\
+             # ({1}, or {1|title} / {1|upper} / {1|lower} to change case):\n\
+             # [[models]]\n\
+             # pattern = 'claude-(opus|sonnet)-(\\d+)-(\\d+)'\n\
+             # regex = true\n\
+             # display_name = \"{1|title} {2}.{3}\"\n\
+             \n\
+             # Claude model ids not listed here are named automatically\n";
 
         // Create parent directory if it doesn't exist
         if let Some(parent) = path.as_ref().parent() {
@@ -136,100 +258,147 @@ impl ModelConfig {
 
 impl Default for ModelConfig {
     fn default() -> Self {
+        // Claude model ids are handled by `parse_claude_model_id`; only names
+        // it can't derive need an entry here
         Self {
             model_entries: vec![
-                // 1M context models — more specific patterns first
-                // Claude Code format: claude-opus-4-6[1m]
-                ModelEntry {
-                    pattern: "opus-4-6[1m]".to_string(),
-                    display_name: "Opus 4.6 1M".to_string(),
-                    context_limit: 1_000_000,
-                },
-                ModelEntry {
-                    pattern: "opus-4.6[1m]".to_string(),
-                    display_name: "Opus 4.6 1M".to_string(),
-                    context_limit: 1_000_000,
-                },
-                ModelEntry {
-                    pattern: "sonnet-4-6[1m]".to_string(),
-                    display_name: "Sonnet 4.6 1M".to_string(),
-                    context_limit: 1_000_000,
-                },
                 // Sub2API proxy format: 1mopus, 1msonnet
-                ModelEntry {
-                    pattern: "1mopus".to_string(),
-                    display_name: "Opus 4.6 1M".to_string(),
-                    context_limit: 1_000_000,
-                },
-                ModelEntry {
-                    pattern: "1msonnet".to_string(),
-                    display_name: "Sonnet 4.6 1M".to_string(),
-                    context_limit: 1_000_000,
-                },
-                // Generic 1M fallback
-                ModelEntry {
-                    pattern: "[1m]".to_string(),
-                    display_name: "Claude 1M".to_string(),
-                    context_limit: 1_000_000,
-                },
-                // Claude 4.x series — specific version first
-                ModelEntry {
-                    pattern: "opus-4-6".to_string(),
-                    display_name: "Opus 4.6".to_string(),
-                    context_limit: 200_000,
-                },
-                ModelEntry {
-                    pattern: "opus-4.6".to_string(),
-                    display_name: "Opus 4.6".to_string(),
-                    context_limit: 200_000,
-                },
-                ModelEntry {
-                    pattern: "sonnet-4-6".to_string(),
-                    display_name: "Sonnet 4.6".to_string(),
-                    context_limit: 200_000,
-                },
-                ModelEntry {
-                    pattern: "opus-4-5".to_string(),
-                    display_name: "Opus 4.5".to_string(),
-                    context_limit: 200_000,
-                },
-                ModelEntry {
-                    pattern: "sonnet-4-5".to_string(),
-                    display_name: "Sonnet 4.5".to_string(),
-                    context_limit: 200_000,
-                },
-                ModelEntry {
-                    pattern: "haiku-4-5".to_string(),
-                    display_name: "Haiku 4.5".to_string(),
-                    context_limit: 200_000,
-                },
-                ModelEntry {
-                    pattern: "claude-3-7-sonnet".to_string(),
-                    display_name: "Sonnet 3.7".to_string(),
-                    context_limit: 200_000,
-                },
+                ModelEntry::substring("1mopus", "Opus 4.6 1M", 1_000_000),
+                ModelEntry::substring("1msonnet", "Sonnet 4.6 1M", 1_000_000),
                 // Third-party models
-                ModelEntry {
-                    pattern: "glm-4.5".to_string(),
-                    display_name: "GLM-4.5".to_string(),
-                    context_limit: 128_000,
-                },
-                ModelEntry {
-                    pattern: "kimi-k2-turbo".to_string(),
-                    display_name: "Kimi K2 Turbo".to_string(),
-                    context_limit: 128_000,
-                },
-                ModelEntry {
-                    pattern: "kimi-k2".to_string(),
-                    display_name: "Kimi K2".to_string(),
-                    context_limit: 128_000,
-                },
-                ModelEntry {
-                    pattern: "qwen3-coder".to_string(),
-                    display_name: "Qwen Coder".to_string(),
-                    context_limit: 256_000,
-                },
+                ModelEntry::substring("glm-4.5", "GLM-4.5", 128_000),
+                ModelEntry::substring("kimi-k2-turbo", "Kimi K2 Turbo", 128_000),
+                ModelEntry::substring("kimi-k2", "Kimi K2", 128_000),
+                ModelEntry::substring("qwen3-coder", "Qwen Coder", 256_000),
             ],
         }
     }
 }
+
+/// Derive a display name and context limit from a Claude model id.
+///
+/// Understands current ids (`claude-opus-4-6`, `claude-sonnet-4-5-20250929`),
+/// legacy ones (`claude-3-7-sonnet-20250219`), provider-prefixed forms
+/// (`us.anthropic.claude-sonnet-4-5-20250929-v1:0`, `claude-opus-4-1@20250805`)
+/// and the `[1m]` suffix for the 1M context variant.
+pub fn parse_claude_model_id(model_id: &str) -> Option<(String, u32)> {
+    let model = ClaudeModel::parse(model_id)?;
+    Some((model.display_name(), model.context_limit()))
+}
+
+/// Family and version of a Claude model id
+struct ClaudeModel {
+    family: String,
+    major: u32,
+    minor: Option<u32>,
+    /// `[1m]` variant with the 1M token context window
+    long_context: bool,
+}
+
+impl ClaudeModel {
+    fn parse(model_id: &str) -> Option<Self> {
+        static FAMILY_FIRST: OnceLock<Regex> = OnceLock::new();
+        static VERSION_FIRST: OnceLock<Regex> = OnceLock::new();
+
+        let id = model_id.to_lowercase();
+        let family_first = FAMILY_FIRST.get_or_init(|| {
+            Regex::new(r"(opus|sonnet|haiku)-(\d+)(?:[-.](\d{1,2})\b)?").expect("valid regex")
+        });
+        let version_first = VERSION_FIRST.get_or_init(|| {
+            Regex::new(r"claude-(\d+)(?:[-.](\d{1,2}))?-(opus|sonnet|haiku)").expect("valid regex")
+        });
+
+        let (family, major, minor) = if let Some(caps) = version_first.captures(&id) {
+            (caps.get(3)?, caps.get(1)?, caps.get(2))
+        } else {
+            let caps = family_first.captures(&id)?;
+            (caps.get(1)?, caps.get(2)?, caps.get(3))
+        };
+
+        Some(Self {
+            family: family.as_str().to_string(),
+            major: major.as_str().parse().ok()?,
+            minor: minor.and_then(|m| m.as_str().parse().ok()),
+            long_context: id.contains("[1m]"),
+        })
+    }
+
+    fn display_name(&self) -> String {
+        let mut name = format!("{} {}", title_case(&self.family), self.major);
+        if let Some(minor) = self.minor {
+            name.push_str(&format!(".{}", minor));
+        }
+        if self.long_context {
+            name.push_str(" 1M");
+        }
+        name
+    }
+
+    fn context_limit(&self) -> u32 {
+        if self.long_context {
+            1_000_000
+        } else {
+            DEFAULT_CONTEXT_LIMIT
+        }
+    }
+
+    /// Anthropic list prices for input and output, per million tokens
+    fn pricing(&self) -> ModelPricing {
+        let version = (self.major, self.minor.unwrap_or(0));
+        let (input, output) = match self.family.as_str() {
+            "opus" if version >= (4, 5) => (5.0, 25.0),
+            "opus" => (15.0, 75.0),
+            "haiku" if version >= (4, 0) => (1.0, 5.0),
+            "haiku" if version >= (3, 5) => (0.8, 4.0),
+            "haiku" => (0.25, 1.25),
+            _ => (3.0, 15.0),
+        };
+        ModelPricing {
+            input,
+            output,
+            cache_write: None,
+            cache_read: None,
+        }
+    }
+}
+
+/// Expand `{N}` and `{N|filter}` references to capture groups. Filters:
+/// `title`, `upper`, `lower`. Anything else in braces is kept as written.
+fn expand_captures(template: &str, caps: &regex::Captures) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('}') else {
+            out.push_str(&rest[open..]);
+            return out;
+        };
+
+        let reference = &after[..close];
+        let (group, filter) = reference.split_once('|').unwrap_or((reference, ""));
+        match group.parse::<usize>() {
+            Ok(index) => {
+                let value = caps.get(index).map(|m| m.as_str()).unwrap_or("");
+                out.push_str(&match filter {
+                    "title" => title_case(value),
+                    "upper" => value.to_uppercase(),
+                    "lower" => value.to_lowercase(),
+                    _ => value.to_string(),
+                });
+            }
+            Err(_) => out.push_str(&rest[open..open + close + 2]),
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn title_case(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,141 @@
+//! Where Claude Code's files and this tool's own files live.
+//!
+//! Claude Code directory (`settings.json`, `.credentials.json`):
+//! 1. `$CLAUDE_CONFIG_DIR`
+//! 2. `~/.claude`
+//!
+//! Config directory (`config.toml`, `models.toml`, `themes/`, the
+//! subscription key file), first match wins:
+//! 1. `--config-dir`
+//! 2. `$CLAUDE_CONFIG_DIR/micusubcodeline`, so each Claude account gets its
+//!    own settings and caches. While that directory doesn't exist, an
+//!    existing `~/.claude/micusubcodeline` is used instead, so setting the
+//!    variable doesn't hide a config created before.
+//! 3. `$XDG_CONFIG_HOME/micusubcodeline` (default `~/.config/micusubcodeline`)
+//!    on Linux, if that directory exists
+//! 4. `~/.claude/micusubcodeline`
+//!
+//! Caches and recorded history live in the config directory, except with
+//! the XDG layout, where they go to `$XDG_CACHE_HOME/micusubcodeline` and
+//! `$XDG_DATA_HOME/micusubcodeline`.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const APP_DIR: &str = "micusubcodeline";
+const CLAUDE_CONFIG_DIR_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Which rule picked the config directory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigDirSource {
+    Flag,
+    ClaudeConfigDir,
+    /// `~/.claude/micusubcodeline`, because `$CLAUDE_CONFIG_DIR` has no
+    /// config directory of its own yet
+    Legacy,
+    Xdg,
+    Home,
+}
+
+impl ConfigDirSource {
+    pub fn describe(self) -> &'static str {
+        match self {
+            ConfigDirSource::Flag => "--config-dir",
+            ConfigDirSource::ClaudeConfigDir => "CLAUDE_CONFIG_DIR",
+            ConfigDirSource::Legacy => {
+                "~/.claude, CLAUDE_CONFIG_DIR has no micusubcodeline directory yet"
+            }
+            ConfigDirSource::Xdg => "XDG_CONFIG_HOME",
+            ConfigDirSource::Home => "default",
+        }
+    }
+}
+
+/// Use `dir` as the config directory for the rest of the process
+/// (`--config-dir`). Only the first call has an effect.
+pub fn set_config_dir(dir: PathBuf) {
+    let _ = CONFIG_DIR_OVERRIDE.set(dir);
+}
+
+/// The `--config-dir` override, if one was given
+pub fn config_dir_override() -> Option<&'static PathBuf> {
+    CONFIG_DIR_OVERRIDE.get()
+}
+
+/// Claude Code's own directory
+pub fn claude_dir() -> Option<PathBuf> {
+    env_dir(CLAUDE_CONFIG_DIR_ENV).or_else(|| Some(dirs::home_dir()?.join(".claude")))
+}
+
+/// Config file of CCometixLine, the tool this project was forked from
+pub fn upstream_config_path() -> Option<PathBuf> {
+    Some(claude_dir()?.join("ccline").join("config.toml"))
+}
+
+/// This tool's config directory and the rule that selected it
+pub fn resolve_config_dir() -> Option<(PathBuf, ConfigDirSource)> {
+    if let Some(dir) = CONFIG_DIR_OVERRIDE.get() {
+        return Some((dir.clone(), ConfigDirSource::Flag));
+    }
+    if let Some(dir) = env_dir(CLAUDE_CONFIG_DIR_ENV) {
+        let dir = dir.join(APP_DIR);
+        let legacy = dirs::home_dir()
+            .map(|home| home.join(".claude").join(APP_DIR))
+            .filter(|legacy| !dir.is_dir() && legacy.is_dir() && *legacy != dir);
+        return Some(match legacy {
+            Some(legacy) => (legacy, ConfigDirSource::Legacy),
+            None => (dir, ConfigDirSource::ClaudeConfigDir),
+        });
+    }
+    if let Some(dir) = xdg_dir("XDG_CONFIG_HOME", ".config").filter(|dir| dir.is_dir()) {
+        return Some((dir, ConfigDirSource::Xdg));
+    }
+    Some((
+        dirs::home_dir()?.join(".claude").join(APP_DIR),
+        ConfigDirSource::Home,
+    ))
+}
+
+/// This tool's config directory
+pub fn config_dir() -> Option<PathBuf> {
+    resolve_config_dir().map(|(dir, _)| dir)
+}
+
+/// Directory for caches that can be deleted at any time
+pub fn cache_dir() -> Option<PathBuf> {
+    layout_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// Directory for recorded data such as usage history
+pub fn data_dir() -> Option<PathBuf> {
+    layout_dir("XDG_DATA_HOME", ".local/share")
+}
+
+fn layout_dir(xdg_var: &str, xdg_default: &str) -> Option<PathBuf> {
+    match resolve_config_dir()? {
+        (_, ConfigDirSource::Xdg) => xdg_dir(xdg_var, xdg_default),
+        (dir, _) => Some(dir),
+    }
+}
+
+/// `$var/micusubcodeline`, or `~/<default>/micusubcodeline` when `var` is
+/// unset; Linux only
+fn xdg_dir(var: &str, default: &str) -> Option<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    // XDG treats relative paths as unset
+    let base = env_dir(var)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| Some(dirs::home_dir()?.join(default)))?;
+    Some(base.join(APP_DIR))
+}
+
+/// A directory from an environment variable, if set and not empty
+fn env_dir(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
@@ -0,0 +1,305 @@
+//! Project-local overrides from a `.micusubcodeline.toml` found by walking
+//! up from the workspace directory.
+//!
+//! Every field is optional and merged field by field over the global
+//! config: `theme` restyles the segments (icons, colors, style) while
+//! keeping their options, `[style]` keys replace the global ones,
+//! `[[segments]]` entries are matched by `id` (and `name` for custom
+//! segments) with options merged key by key, and `[[models]]` entries take
+//! priority over `models.toml`.
+//!
+//! A project file comes with the repository, so it is not trusted with
+//! anything that runs commands or decides where credentials are sent: it
+//! can't add custom segments or set their options, and options naming a
+//! command or an endpoint URL are dropped with a warning (see `--check`).
+
+use super::models::ModelEntry;
+use super::types::{
+    AnsiColor, ColorConfig, Config, IconConfig, SegmentConfig, SegmentId, StyleMode,
+    TextStyleConfig,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_CONFIG_FILE: &str = ".micusubcodeline.toml";
+
+/// Whether a project file may not set the option `key`: commands, and URLs
+/// the Subscription and Usage segments send API keys or OAuth tokens to
+fn is_restricted_option(key: &str) -> bool {
+    key == "command" || key.ends_with("_url") || key.ends_with("_urls")
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ProjectConfig {
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub style: StyleOverride,
+    #[serde(default)]
+    pub segments: Vec<SegmentOverride>,
+    #[serde(default)]
+    pub models: Vec<ModelEntry>,
+    /// Settings dropped by `load` because a project file may not make them
+    #[serde(skip)]
+    pub ignored: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StyleOverride {
+    pub mode: Option<StyleMode>,
+    pub separator: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SegmentOverride {
+    pub id: SegmentId,
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub icon: IconOverride,
+    #[serde(default)]
+    pub colors: ColorOverride,
+    #[serde(default)]
+    pub styles: StylesOverride,
+    #[serde(default)]
+    pub options: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct IconOverride {
+    pub plain: Option<String>,
+    pub nerd_font: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ColorOverride {
+    pub icon: Option<AnsiColor>,
+    pub text: Option<AnsiColor>,
+    pub background: Option<AnsiColor>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StylesOverride {
+    pub text_bold: Option<bool>,
+}
+
+impl ProjectConfig {
+    /// Nearest `.micusubcodeline.toml` in `start_dir` or one of its ancestors
+    pub fn discover(start_dir: &Path) -> Option<PathBuf> {
+        start_dir
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Parse a project file, dropping the settings it may not make
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let mut project: Self = toml::from_str(&content)?;
+        project.drop_restricted();
+        Ok(project)
+    }
+
+    fn drop_restricted(&mut self) {
+        for (index, segment) in self.segments.iter_mut().enumerate() {
+            let custom = segment.id == SegmentId::Custom;
+            let mut keys: Vec<String> = segment
+                .options
+                .keys()
+                .filter(|key| {
+                    if custom {
+                        key.as_str() != "name"
+                    } else {
+                        is_restricted_option(key)
+                    }
+                })
+                .cloned()
+                .collect();
+            keys.sort();
+            for key in keys {
+                segment.options.remove(&key);
+                self.ignored.push(format!(
+                    "segments[{}] ({}): option '{}' can only be set in the global config",
+                    index,
+                    segment.id.key(),
+                    key
+                ));
+            }
+        }
+    }
+
+    /// Why parts of this project file don't apply over `config`: dropped
+    /// settings, and custom segments the global config doesn't define
+    pub fn warnings(&self, config: &Config) -> Vec<String> {
+        let mut warnings = self.ignored.clone();
+        for (index, segment) in self.segments.iter().enumerate() {
+            if let Some(name) = segment.custom_name() {
+                if Self::find_segment(config, segment).is_none() {
+                    warnings.push(format!(
+                        "segments[{}] (custom): no custom segment named '{}' in the global config; project files can't add custom segments",
+                        index, name
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    fn find_segment<'a>(
+        config: &'a Config,
+        segment: &SegmentOverride,
+    ) -> Option<&'a SegmentConfig> {
+        let name = segment.custom_name();
+        config
+            .segments
+            .iter()
+            .find(|s| s.id == segment.id && s.custom_name() == name)
+    }
+
+    /// The project config for a workspace directory, if there is one and it
+    /// parses. Broken files are ignored here and reported by `--check`.
+    pub fn for_workspace(current_dir: &str) -> Option<(PathBuf, Self)> {
+        let path = Self::discover(Path::new(current_dir))?;
+        let project = Self::load(&path).ok()?;
+        Some((path, project))
+    }
+
+    /// Layer these overrides over `config`
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(theme) = &self.theme {
+            Self::apply_theme(config, theme);
+        }
+
+        if let Some(mode) = self.style.mode {
+            config.style.mode = mode;
+        }
+        if let Some(separator) = &self.style.separator {
+            config.style.separator = separator.clone();
+        }
+
+        for segment in &self.segments {
+            let name = segment.custom_name();
+            let existing = config
+                .segments
+                .iter_mut()
+                .find(|s| s.id == segment.id && s.custom_name() == name);
+            match existing {
+                Some(existing) => segment.apply(existing),
+                // Custom segments run commands; only the global config adds them
+                None if segment.id == SegmentId::Custom => {}
+                None => {
+                    let mut added = Self::base_segment(config, segment.id);
+                    segment.apply(&mut added);
+                    config.segments.push(added);
+                }
+            }
+        }
+    }
+
+    /// Take the style and each segment's look from a theme, keeping the
+    /// segments' order, enabled state and options
+    fn apply_theme(config: &mut Config, theme: &str) {
+        let preset = crate::ui::themes::ThemePresets::get_theme(theme);
+        config.theme = theme.to_string();
+        config.style = preset.style;
+        for segment in &mut config.segments {
+            let themed = preset
+                .segments
+                .iter()
+                .find(|s| s.id == segment.id && s.custom_name() == segment.custom_name())
+                .or_else(|| preset.segments.iter().find(|s| s.id == segment.id));
+            if let Some(themed) = themed {
+                segment.icon = themed.icon.clone();
+                segment.colors = themed.colors.clone();
+                segment.styles = themed.styles.clone();
+            }
+        }
+    }
+
+    /// Starting point for a segment the global config doesn't have: the
+    /// current theme's defaults, or a bare segment
+    fn base_segment(config: &Config, id: SegmentId) -> SegmentConfig {
+        crate::ui::themes::ThemePresets::get_theme(&config.theme)
+            .segments
+            .into_iter()
+            .find(|s| s.id == id)
+            .map(|mut segment| {
+                if id == SegmentId::Custom {
+                    segment.options.clear();
+                }
+                segment
+            })
+            .unwrap_or_else(|| SegmentConfig {
+                id,
+                enabled: true,
+                icon: IconConfig {
+                    plain: String::new(),
+                    nerd_font: String::new(),
+                },
+                colors: ColorConfig {
+                    icon: None,
+                    text: None,
+                    background: None,
+                },
+                styles: TextStyleConfig::default(),
+                options: HashMap::new(),
+            })
+    }
+}
+
+impl SegmentOverride {
+    /// Same as `SegmentConfig::custom_name`
+    fn custom_name(&self) -> Option<&str> {
+        if self.id != SegmentId::Custom {
+            return None;
+        }
+        Some(
+            self.options
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("custom"),
+        )
+    }
+
+    fn apply(&self, segment: &mut SegmentConfig) {
+        if let Some(enabled) = self.enabled {
+            segment.enabled = enabled;
+        }
+        if let Some(plain) = &self.icon.plain {
+            segment.icon.plain = plain.clone();
+        }
+        if let Some(nerd_font) = &self.icon.nerd_font {
+            segment.icon.nerd_font = nerd_font.clone();
+        }
+        if self.colors.icon.is_some() {
+            segment.colors.icon = self.colors.icon.clone();
+        }
+        if self.colors.text.is_some() {
+            segment.colors.text = self.colors.text.clone();
+        }
+        if self.colors.background.is_some() {
+            segment.colors.background = self.colors.background.clone();
+        }
+        if let Some(bold) = self.styles.text_bold {
+            segment.styles.text_bold = bold;
+        }
+        for (key, value) in &self.options {
+            merge_option(segment.options.entry(key.clone()).or_default(), value);
+        }
+    }
+}
+
+/// Merge tables key by key; any other value replaces the existing one
+fn merge_option(target: &mut serde_json::Value, value: &serde_json::Value) {
+    match (target, value) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(value)) => {
+            for (key, value) in value {
+                merge_option(
+                    target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                    value,
+                );
+            }
+        }
+        (target, value) => *target = value.clone(),
+    }
+}
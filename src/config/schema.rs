@@ -0,0 +1,550 @@
+//! Option schemas for the free-form `[segments.options]` tables.
+//!
+//! Each segment module declares the options it reads as an `OPTIONS`
+//! table; `segment_options` adds the options every segment understands.
+//! The schemas drive `--check` (unknown keys, wrong types and out-of-range
+//! values, reported with line numbers), the JSON Schema export for editor
+//! completion, and the options editor in the TUI.
+
+use super::types::{ColorRule, SegmentId};
+use crate::core::segments;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use toml::Spanned;
+
+/// What values an option accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionKind {
+    Bool,
+    /// Whole number within `min..=max`
+    Integer {
+        min: u64,
+        max: u64,
+    },
+    String,
+    /// One of a fixed set of strings
+    Enum(&'static [&'static str]),
+    /// Array of strings, limited to the given values if any
+    StringList(Option<&'static [&'static str]>),
+    /// Table of strings, limited to the given keys if any
+    StringMap(Option<&'static [&'static str]>),
+    /// Array of color rule tables (see `ColorRule`)
+    ColorRules,
+}
+
+/// Value used when an option is not set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionDefault {
+    /// No value; the segment's built-in behavior applies
+    None,
+    Bool(bool),
+    Integer(u64),
+    Str(&'static str),
+    List(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub kind: OptionKind,
+    pub default: OptionDefault,
+    pub description: &'static str,
+}
+
+/// Issue found in a config file's segment options
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionIssue {
+    /// 1-based line in the file
+    pub line: usize,
+    /// Unknown options are only warnings: they are ignored, not misread
+    pub is_error: bool,
+    pub message: String,
+}
+
+impl OptionDefault {
+    pub fn to_value(self) -> Option<Value> {
+        match self {
+            OptionDefault::None => None,
+            OptionDefault::Bool(b) => Some(Value::Bool(b)),
+            OptionDefault::Integer(n) => Some(Value::from(n)),
+            OptionDefault::Str(s) => Some(Value::from(s)),
+            OptionDefault::List(items) => Some(Value::from(items.to_vec())),
+        }
+    }
+}
+
+impl OptionKind {
+    /// Short description of the accepted values, e.g. `integer 1..=60000`
+    pub fn describe(self) -> String {
+        match self {
+            OptionKind::Bool => "boolean".to_string(),
+            OptionKind::Integer { min, max } => format!("integer {}..={}", min, max),
+            OptionKind::String => "string".to_string(),
+            OptionKind::Enum(values) => format!("one of {}", values.join(", ")),
+            OptionKind::StringList(Some(values)) => {
+                format!("list of {}", values.join(", "))
+            }
+            OptionKind::StringList(None) => "list of strings".to_string(),
+            OptionKind::StringMap(Some(keys)) => format!("table with keys {}", keys.join(", ")),
+            OptionKind::StringMap(None) => "table of strings".to_string(),
+            OptionKind::ColorRules => "list of color rules".to_string(),
+        }
+    }
+}
+
+impl OptionSpec {
+    /// Check a value against this option's kind
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        let expected = |what: &str| Err(format!("expected {}, found {}", what, type_name(value)));
+        match self.kind {
+            OptionKind::Bool if !value.is_boolean() => expected("a boolean"),
+            OptionKind::Integer { min, max } => match value {
+                Value::Number(n) if n.is_f64() => expected("an integer"),
+                Value::Number(n) => match n.as_u64().filter(|n| (min..=max).contains(n)) {
+                    Some(_) => Ok(()),
+                    None => Err(format!("{} is out of range ({}..={})", n, min, max)),
+                },
+                _ => expected("an integer"),
+            },
+            OptionKind::String if !value.is_string() => expected("a string"),
+            OptionKind::Enum(values) => match value.as_str() {
+                Some(s) if values.contains(&s) => Ok(()),
+                Some(s) => Err(format!("'{}' is not one of: {}", s, values.join(", "))),
+                None => expected("a string"),
+            },
+            OptionKind::StringList(values) => {
+                let Some(items) = value.as_array() else {
+                    return expected("a list of strings");
+                };
+                for item in items {
+                    match (item.as_str(), values) {
+                        (None, _) => {
+                            return Err(format!(
+                                "expected a list of strings, found {} in the list",
+                                type_name(item)
+                            ))
+                        }
+                        (Some(s), Some(values)) if !values.contains(&s) => {
+                            return Err(format!("'{}' is not one of: {}", s, values.join(", ")))
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(())
+            }
+            OptionKind::StringMap(keys) => {
+                let Some(table) = value.as_object() else {
+                    return expected("a table");
+                };
+                for (key, item) in table {
+                    if let Some(keys) = keys.filter(|keys| !keys.contains(&key.as_str())) {
+                        return Err(format!(
+                            "unknown key '{}' (expected one of: {})",
+                            key,
+                            keys.join(", ")
+                        ));
+                    }
+                    if !item.is_string() {
+                        return Err(format!(
+                            "expected a string for '{}', found {}",
+                            key,
+                            type_name(item)
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            OptionKind::ColorRules => {
+                let rules: Vec<ColorRule> = serde_json::from_value(value.clone())
+                    .map_err(|e| format!("invalid color rules: {}", e))?;
+                match rules.iter().find(|r| r.parse_condition().is_none()) {
+                    Some(rule) => Err(format!("invalid color rule condition '{}'", rule.when)),
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Options every segment understands
+fn common_options(id: SegmentId) -> [OptionSpec; 4] {
+    [
+        OptionSpec {
+            name: "format",
+            kind: OptionKind::String,
+            default: OptionDefault::None,
+            description: "Template for the main text, e.g. \"{branch}\"",
+        },
+        OptionSpec {
+            name: "secondary_format",
+            kind: OptionKind::String,
+            default: OptionDefault::None,
+            description: "Template for the secondary text",
+        },
+        OptionSpec {
+            name: "deadline_ms",
+            kind: OptionKind::Integer {
+                min: 1,
+                max: 60_000,
+            },
+            default: OptionDefault::Integer(crate::core::statusline::default_deadline_ms(id)),
+            description: "Time budget in ms; a late segment shows its cached value",
+        },
+        OptionSpec {
+            name: "color_rules",
+            kind: OptionKind::ColorRules,
+            default: OptionDefault::None,
+            description: "Colors applied when a metadata condition matches",
+        },
+    ]
+}
+
+/// All options a segment reads: its own, then the common ones
+pub fn segment_options(id: SegmentId) -> Vec<OptionSpec> {
+    let own: &[OptionSpec] = match id {
+        SegmentId::Directory => segments::directory::OPTIONS,
+        SegmentId::Git => segments::git::OPTIONS,
+        SegmentId::ContextWindow => segments::context_window::OPTIONS,
+        SegmentId::Usage => segments::usage::OPTIONS,
+        SegmentId::Cost => segments::cost::OPTIONS,
+        SegmentId::Subscription => segments::subscription::OPTIONS,
+        SegmentId::Custom => segments::custom::OPTIONS,
+        SegmentId::Model | SegmentId::Session | SegmentId::OutputStyle | SegmentId::Update => &[],
+    };
+    own.iter().copied().chain(common_options(id)).collect()
+}
+
+/// The schema of one option of a segment
+pub fn option_spec(id: SegmentId, name: &str) -> Option<OptionSpec> {
+    segment_options(id)
+        .into_iter()
+        .find(|spec| spec.name == name)
+}
+
+/// The parts of a config or project file `check_options` looks at, with
+/// positions
+#[derive(Deserialize)]
+struct SpannedFile {
+    #[serde(default)]
+    segments: Vec<SpannedSegment>,
+}
+
+#[derive(Deserialize)]
+struct SpannedSegment {
+    id: Spanned<String>,
+    #[serde(default)]
+    options: BTreeMap<Spanned<String>, Spanned<toml::Value>>,
+}
+
+/// Validate the segment options of a config or project file. Files that
+/// don't parse, or use the pre-`[[segments]]` layout, yield no issues;
+/// loading them reports the problem.
+pub fn check_options(content: &str) -> Vec<OptionIssue> {
+    let is_segment_list = toml::from_str::<toml::Value>(content)
+        .is_ok_and(|value| value.get("segments").is_none_or(|s| s.is_array()));
+    if !is_segment_list {
+        return Vec::new();
+    }
+    let Ok(file) = toml::from_str::<SpannedFile>(content) else {
+        return Vec::new();
+    };
+
+    let line_of = |offset: usize| content[..offset].matches('\n').count() + 1;
+    let mut issues = Vec::new();
+    for (i, segment) in file.segments.iter().enumerate() {
+        // Unknown ids are reported by the migration
+        let Ok(id) = SegmentId::deserialize(toml::Value::String(segment.id.get_ref().clone()))
+        else {
+            continue;
+        };
+        let label = match segment
+            .options
+            .iter()
+            .find(|(key, _)| key.get_ref() == "name")
+        {
+            Some((_, name)) if id == SegmentId::Custom => format!(
+                "segments[{}] (custom '{}')",
+                i,
+                name.get_ref().as_str().unwrap_or("custom")
+            ),
+            _ => format!("segments[{}] ({})", i, segment.id.get_ref()),
+        };
+
+        let specs = segment_options(id);
+        for (key, value) in &segment.options {
+            let Some(spec) = specs.iter().find(|spec| spec.name == key.get_ref()) else {
+                issues.push(OptionIssue {
+                    line: line_of(key.span().start),
+                    is_error: false,
+                    message: format!("{}: unknown option '{}'", label, key.get_ref()),
+                });
+                continue;
+            };
+            let json = serde_json::to_value(value.get_ref()).unwrap_or(Value::Null);
+            if let Err(e) = spec.validate(&json) {
+                issues.push(OptionIssue {
+                    line: line_of(value.span().start),
+                    is_error: true,
+                    message: format!("{}: option '{}': {}", label, spec.name, e),
+                });
+            }
+        }
+    }
+    issues.sort_by_key(|issue| issue.line);
+    issues
+}
+
+/// JSON Schema (draft-07) of the config file, for editor completion
+pub fn json_schema() -> Value {
+    let ids: Vec<&str> = SegmentId::ALL.iter().map(|id| id.key()).collect();
+    let color = json!({
+        "description": "ANSI color: { c16 = 0-15 }, { c256 = 0-255 } or { r, g, b }",
+        "oneOf": [
+            object_schema(json!({ "c16": { "type": "integer", "minimum": 0, "maximum": 15 } })),
+            object_schema(json!({ "c256": { "type": "integer", "minimum": 0, "maximum": 255 } })),
+            object_schema(json!({
+                "r": { "type": "integer", "minimum": 0, "maximum": 255 },
+                "g": { "type": "integer", "minimum": 0, "maximum": 255 },
+                "b": { "type": "integer", "minimum": 0, "maximum": 255 },
+            })),
+        ],
+    });
+    let color_rule = object_schema(json!({
+        "when": {
+            "type": "string",
+            "description": "Condition on segment metadata, e.g. \"percentage >= 80\"",
+        },
+        "icon": { "$ref": "#/definitions/color" },
+        "text": { "$ref": "#/definitions/color" },
+        "background": { "$ref": "#/definitions/color" },
+        "text_bold": { "type": "boolean" },
+    }));
+
+    let mut definitions = Map::new();
+    definitions.insert("color".to_string(), color);
+    definitions.insert("color_rule".to_string(), color_rule);
+
+    let mut per_segment = Vec::new();
+    for id in SegmentId::ALL {
+        let properties: Map<String, Value> = segment_options(id)
+            .iter()
+            .map(|spec| (spec.name.to_string(), option_json_schema(spec)))
+            .collect();
+        let name = format!("{}_options", id.key());
+        definitions.insert(name.clone(), object_schema(Value::Object(properties)));
+        per_segment.push(json!({
+            "if": { "properties": { "id": { "const": id.key() } } },
+            "then": { "properties": { "options": { "$ref": format!("#/definitions/{}", name) } } },
+        }));
+    }
+
+    let mut segment = object_schema(json!({
+        "id": { "enum": ids },
+        "enabled": { "type": "boolean" },
+        "icon": object_schema(json!({
+            "plain": { "type": "string" },
+            "nerd_font": { "type": "string" },
+        })),
+        "colors": object_schema(json!({
+            "icon": { "$ref": "#/definitions/color" },
+            "text": { "$ref": "#/definitions/color" },
+            "background": { "$ref": "#/definitions/color" },
+        })),
+        "styles": object_schema(json!({ "text_bold": { "type": "boolean" } })),
+        "options": { "type": "object" },
+    }));
+    segment["required"] = json!(["id"]);
+    segment["allOf"] = Value::Array(per_segment);
+    definitions.insert("segment".to_string(), segment);
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "micusubcodeline config",
+        "type": "object",
+        "properties": {
+            "version": { "type": "integer", "description": "Config schema version" },
+            "theme": { "type": "string" },
+            "style": object_schema(json!({
+                "mode": { "enum": ["plain", "nerd_font", "powerline"] },
+                "separator": { "type": "string" },
+            })),
+            "segments": { "type": "array", "items": { "$ref": "#/definitions/segment" } },
+            "models": { "type": "array", "items": { "type": "object" } },
+        },
+        "definitions": definitions,
+    })
+}
+
+/// A closed table with the given properties
+fn object_schema(properties: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+fn option_json_schema(spec: &OptionSpec) -> Value {
+    let mut schema = match spec.kind {
+        OptionKind::Bool => json!({ "type": "boolean" }),
+        OptionKind::Integer { min, max } => {
+            json!({ "type": "integer", "minimum": min, "maximum": max })
+        }
+        OptionKind::String => json!({ "type": "string" }),
+        OptionKind::Enum(values) => json!({ "enum": values }),
+        OptionKind::StringList(Some(values)) => {
+            json!({ "type": "array", "items": { "enum": values } })
+        }
+        OptionKind::StringList(None) => json!({ "type": "array", "items": { "type": "string" } }),
+        OptionKind::StringMap(Some(keys)) => {
+            let properties: Map<String, Value> = keys
+                .iter()
+                .map(|key| (key.to_string(), json!({ "type": "string" })))
+                .collect();
+            object_schema(Value::Object(properties))
+        }
+        OptionKind::StringMap(None) => {
+            json!({ "type": "object", "additionalProperties": { "type": "string" } })
+        }
+        OptionKind::ColorRules => {
+            json!({ "type": "array", "items": { "$ref": "#/definitions/color_rule" } })
+        }
+    };
+    schema["description"] = Value::from(spec.description);
+    if let Some(default) = spec.default.to_value() {
+        schema["default"] = default;
+    }
+    schema
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "nothing",
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_f64() => "a float",
+        Value::Number(_) => "an integer",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "a table",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"theme = "default"
+
+[[segments]]
+id = "git"
+enabled = true
+
+[segments.options]
+show_sha = "yes"
+status_timeout_ms = 1.5
+
+[[segments]]
+id = "usage"
+enabled = true
+
+[segments.options]
+timeout = 600
+colour = "blue"
+reset_format = "time"
+
+[[segments]]
+id = "custom"
+enabled = true
+
+[segments.options]
+name = "weather"
+command = "curl -s wttr.in"
+timeout_ms = 0
+"#;
+
+    fn issue(line: usize, is_error: bool, message: &str) -> OptionIssue {
+        OptionIssue {
+            line,
+            is_error,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn check_options_reports_issues_on_their_lines() {
+        assert_eq!(
+            check_options(CONFIG),
+            vec![
+                issue(
+                    8,
+                    true,
+                    "segments[0] (git): option 'show_sha': expected a boolean, found a string"
+                ),
+                issue(
+                    9,
+                    true,
+                    "segments[0] (git): option 'status_timeout_ms': expected an integer, found a float"
+                ),
+                issue(
+                    16,
+                    true,
+                    "segments[1] (usage): option 'timeout': 600 is out of range (1..=60)"
+                ),
+                issue(17, false, "segments[1] (usage): unknown option 'colour'"),
+                issue(
+                    27,
+                    true,
+                    "segments[2] (custom 'weather'): option 'timeout_ms': 0 is out of range (1..=60000)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_options_skips_files_it_cannot_place() {
+        // Loading reports these; there are no option lines to point at
+        assert!(check_options("theme = ").is_empty());
+        assert!(check_options("[segments]\nmodel = true\n").is_empty());
+        assert!(
+            check_options("[[segments]]\nid = \"weather\"\n[segments.options]\nx = 1\n").is_empty()
+        );
+    }
+
+    #[test]
+    fn validate_accepts_defaults() {
+        for id in SegmentId::ALL {
+            for spec in segment_options(id) {
+                if let Some(value) = spec.default.to_value() {
+                    assert_eq!(
+                        spec.validate(&value),
+                        Ok(()),
+                        "default of {}.{}",
+                        id.key(),
+                        spec.name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn json_schema_defines_options_of_every_segment() {
+        let schema = json_schema();
+        for id in SegmentId::ALL {
+            let name = format!("{}_options", id.key());
+            let properties = schema["definitions"][&name]["properties"]
+                .as_object()
+                .unwrap_or_else(|| panic!("missing definition {}", name));
+            for spec in segment_options(id) {
+                assert!(
+                    properties.contains_key(spec.name),
+                    "{} lacks '{}'",
+                    name,
+                    spec.name
+                );
+            }
+        }
+    }
+}
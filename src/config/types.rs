@@ -4,6 +4,9 @@ use std::collections::HashMap;
 // Main config structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Schema version, see `config::migration`
+    #[serde(default)]
+    pub version: u32,
     pub style: StyleConfig,
     pub segments: Vec<SegmentConfig>,
     pub theme: String,
@@ -53,6 +56,26 @@ pub struct TextStyleConfig {
     pub text_bold: bool,
 }
 
+/// Dynamic color rule stored in a segment's `color_rules` option.
+/// The first rule whose `when` condition matches the segment's metadata
+/// overrides the segment's colors and bold style.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorRule {
+    /// Condition such as `percentage >= 80` or `status == Dirty`
+    pub when: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<AnsiColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<AnsiColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<AnsiColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_bold: Option<bool>,
+}
+
+/// Comparison operators supported in color rule conditions, longest first
+const RULE_OPERATORS: [&str; 6] = [">=", "<=", "==", "!=", ">", "<"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum AnsiColor {
@@ -74,6 +97,41 @@ pub enum SegmentId {
     OutputStyle,
     Update,
     Subscription,
+    /// User-defined segment rendering a shell command's output
+    Custom,
+}
+
+impl SegmentId {
+    pub const ALL: [SegmentId; 11] = [
+        SegmentId::Model,
+        SegmentId::Directory,
+        SegmentId::Git,
+        SegmentId::ContextWindow,
+        SegmentId::Usage,
+        SegmentId::Cost,
+        SegmentId::Session,
+        SegmentId::OutputStyle,
+        SegmentId::Update,
+        SegmentId::Subscription,
+        SegmentId::Custom,
+    ];
+
+    /// Name used for `id` in config files
+    pub fn key(self) -> &'static str {
+        match self {
+            SegmentId::Model => "model",
+            SegmentId::Directory => "directory",
+            SegmentId::Git => "git",
+            SegmentId::ContextWindow => "context_window",
+            SegmentId::Usage => "usage",
+            SegmentId::Cost => "cost",
+            SegmentId::Session => "session",
+            SegmentId::OutputStyle => "output_style",
+            SegmentId::Update => "update",
+            SegmentId::Subscription => "subscription",
+            SegmentId::Custom => "custom",
+        }
+    }
 }
 
 // Legacy compatibility structure
@@ -86,18 +144,18 @@ pub struct SegmentsConfig {
 }
 
 // Data structures compatible with existing main.rs
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct Model {
     pub id: String,
     pub display_name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct Workspace {
     pub current_dir: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct Cost {
     pub total_cost_usd: Option<f64>,
     pub total_duration_ms: Option<u64>,
@@ -106,12 +164,12 @@ pub struct Cost {
     pub total_lines_removed: Option<u32>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct OutputStyle {
     pub name: String,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Subscription {
     pub group_name: String,
     pub daily_used_usd: f64,
@@ -120,7 +178,7 @@ pub struct Subscription {
     pub resets_in_seconds: Option<i64>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct InputData {
     pub model: Model,
     pub workspace: Workspace,
@@ -305,6 +363,114 @@ impl Config {
     }
 }
 
+impl ColorRule {
+    /// Split the condition into `(key, operator, value)`
+    pub fn parse_condition(&self) -> Option<(&str, &str, &str)> {
+        RULE_OPERATORS.iter().find_map(|op| {
+            let idx = self.when.find(op)?;
+            let key = self.when[..idx].trim();
+            let value = self.when[idx + op.len()..].trim().trim_matches('"');
+            if key.is_empty() {
+                None
+            } else {
+                Some((key, *op, value))
+            }
+        })
+    }
+
+    /// Check the condition against segment metadata. Values are compared
+    /// numerically when both sides parse as numbers, otherwise only `==`
+    /// and `!=` apply as string comparisons.
+    pub fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        let Some((key, op, expected)) = self.parse_condition() else {
+            return false;
+        };
+        let Some(actual) = metadata.get(key).map(|v| v.trim()) else {
+            return false;
+        };
+
+        match (actual.parse::<f64>(), expected.parse::<f64>()) {
+            (Ok(a), Ok(b)) => match op {
+                ">=" => a >= b,
+                "<=" => a <= b,
+                "==" => a == b,
+                "!=" => a != b,
+                ">" => a > b,
+                "<" => a < b,
+                _ => false,
+            },
+            _ => match op {
+                "==" => actual == expected,
+                "!=" => actual != expected,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl SegmentConfig {
+    /// Color rules from the `color_rules` option (invalid entries are ignored)
+    pub fn color_rules(&self) -> Vec<ColorRule> {
+        self.options
+            .get("color_rules")
+            .and_then(|v| v.as_array())
+            .map(|rules| {
+                rules
+                    .iter()
+                    .filter_map(|rule| serde_json::from_value(rule.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Store color rules in the `color_rules` option, removing it when empty
+    pub fn set_color_rules(&mut self, rules: &[ColorRule]) {
+        if rules.is_empty() {
+            self.options.remove("color_rules");
+        } else if let Ok(value) = serde_json::to_value(rules) {
+            self.options.insert("color_rules".to_string(), value);
+        }
+    }
+
+    /// Return a copy of this config with the first matching color rule applied
+    pub fn with_color_rules_applied(&self, metadata: &HashMap<String, String>) -> SegmentConfig {
+        let mut resolved = self.clone();
+        if let Some(rule) = self
+            .color_rules()
+            .into_iter()
+            .find(|rule| rule.matches(metadata))
+        {
+            if rule.icon.is_some() {
+                resolved.colors.icon = rule.icon;
+            }
+            if rule.text.is_some() {
+                resolved.colors.text = rule.text;
+            }
+            if rule.background.is_some() {
+                resolved.colors.background = rule.background;
+            }
+            if let Some(bold) = rule.text_bold {
+                resolved.styles.text_bold = bold;
+            }
+        }
+        resolved
+    }
+
+    /// Name of a custom segment (the `name` option), used to tell custom
+    /// segments apart since they all share `SegmentId::Custom`
+    pub fn custom_name(&self) -> Option<&str> {
+        if self.id != SegmentId::Custom {
+            return None;
+        }
+        Some(
+            self.options
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("custom"),
+        )
+    }
+}
+
 impl PartialEq for AnsiColor {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -327,6 +493,54 @@ impl PartialEq for AnsiColor {
     }
 }
 
+/// xterm's default values for the 16 basic colors
+const XTERM_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+impl AnsiColor {
+    /// Approximate RGB value, using the xterm palette for indexed colors
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        match self {
+            AnsiColor::Color16 { c16 } => XTERM_16[(*c16 as usize).min(15)],
+            AnsiColor::Color256 { c256 } => match *c256 {
+                0..=15 => XTERM_16[*c256 as usize],
+                16..=231 => {
+                    let index = c256 - 16;
+                    let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                    (level(index / 36), level((index / 6) % 6), level(index % 6))
+                }
+                gray => {
+                    let v = 8 + (gray - 232) * 10;
+                    (v, v, v)
+                }
+            },
+            AnsiColor::Rgb { r, g, b } => (*r, *g, *b),
+        }
+    }
+
+    /// `#rrggbb` form of the color
+    pub fn to_hex(&self) -> String {
+        let (r, g, b) = self.to_rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
 impl RawUsage {
     /// Convert raw usage data to normalized format with intelligent token inference
     pub fn normalize(self) -> NormalizedUsage {
@@ -411,6 +625,8 @@ pub type Usage = RawUsage;
 
 #[derive(Deserialize)]
 pub struct Message {
+    pub id: Option<String>,
+    pub model: Option<String>,
     pub usage: Option<Usage>,
 }
 
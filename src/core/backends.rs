@@ -0,0 +1,315 @@
+//! Renderers for targets other than Claude Code's ANSI statusline.
+//!
+//! Segments are laid out the same way as in the ANSI renderer: padded
+//! blocks when a background is set, powerline arrows whose colors bridge
+//! neighbouring backgrounds, and a white separator otherwise.
+
+use crate::config::{AnsiColor, SegmentConfig};
+use crate::core::segments::SegmentData;
+use serde::Serialize;
+
+const POWERLINE_ARROW: &str = "\u{e0b0}";
+
+/// Color of plain (non-powerline) separators, matching the ANSI renderer
+const SEPARATOR_COLOR: AnsiColor = AnsiColor::Color16 { c16: 7 };
+
+/// A segment ready for rendering: color rules applied and icon resolved
+pub struct ResolvedSegment {
+    pub icon: String,
+    pub config: SegmentConfig,
+    pub data: SegmentData,
+}
+
+impl ResolvedSegment {
+    fn background(&self) -> Option<&AnsiColor> {
+        self.config.colors.background.as_ref()
+    }
+}
+
+/// Inline markup languages for status bars and shell prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    /// tmux `#[fg=...,bg=...]` style directives
+    Tmux,
+    /// zsh prompt escapes (`%F{}`, `%K{}`, `%B`)
+    Zsh,
+    /// ANSI sequences wrapped in bash's `\[` `\]` non-printing markers
+    Bash,
+    /// Pango `<span>` markup, used for i3bar and waybar
+    Pango,
+}
+
+impl Markup {
+    /// Wrap `text` in a foreground color, background color and bold style.
+    /// Omitted attributes are inherited from the surrounding markup.
+    fn style(
+        self,
+        text: &str,
+        fg: Option<&AnsiColor>,
+        bg: Option<&AnsiColor>,
+        bold: bool,
+    ) -> String {
+        self.wrap(self.escape(text), fg, bg, bold)
+    }
+
+    /// Like `style`, for content that is already markup
+    fn wrap(
+        self,
+        text: String,
+        fg: Option<&AnsiColor>,
+        bg: Option<&AnsiColor>,
+        bold: bool,
+    ) -> String {
+        if fg.is_none() && bg.is_none() && !bold {
+            return text;
+        }
+
+        match self {
+            Markup::Tmux => {
+                let mut open = Vec::new();
+                let mut close = Vec::new();
+                if let Some(fg) = fg {
+                    open.push(format!("fg={}", tmux_color(fg)));
+                    close.push("fg=default");
+                }
+                if let Some(bg) = bg {
+                    open.push(format!("bg={}", tmux_color(bg)));
+                    close.push("bg=default");
+                }
+                if bold {
+                    open.push("bold".to_string());
+                    close.push("nobold");
+                }
+                format!("#[{}]{}#[{}]", open.join(","), text, close.join(","))
+            }
+            Markup::Zsh => {
+                let mut open = String::new();
+                let mut close = String::new();
+                if let Some(fg) = fg {
+                    open.push_str(&format!("%F{{{}}}", zsh_color(fg)));
+                    close.push_str("%f");
+                }
+                if let Some(bg) = bg {
+                    open.push_str(&format!("%K{{{}}}", zsh_color(bg)));
+                    close.push_str("%k");
+                }
+                if bold {
+                    open.push_str("%B");
+                    close.push_str("%b");
+                }
+                format!("{}{}{}", open, text, close)
+            }
+            Markup::Bash => {
+                let mut open = Vec::new();
+                let mut close = Vec::new();
+                if bold {
+                    open.push("1".to_string());
+                    close.push("22");
+                }
+                if let Some(fg) = fg {
+                    open.push(sgr_color(fg, false));
+                    close.push("39");
+                }
+                if let Some(bg) = bg {
+                    open.push(sgr_color(bg, true));
+                    close.push("49");
+                }
+                format!(
+                    "\\[\\e[{}m\\]{}\\[\\e[{}m\\]",
+                    open.join(";"),
+                    text,
+                    close.join(";")
+                )
+            }
+            Markup::Pango => {
+                let mut attrs = Vec::new();
+                if let Some(fg) = fg {
+                    attrs.push(format!("foreground=\"{}\"", fg.to_hex()));
+                }
+                if let Some(bg) = bg {
+                    attrs.push(format!("background=\"{}\"", bg.to_hex()));
+                }
+                if bold {
+                    attrs.push("weight=\"bold\"".to_string());
+                }
+                format!("<span {}>{}</span>", attrs.join(" "), text)
+            }
+        }
+    }
+
+    /// Escape characters that the target would otherwise interpret
+    fn escape(self, text: &str) -> String {
+        match self {
+            Markup::Tmux => text.replace('#', "##"),
+            Markup::Zsh => text.replace('%', "%%"),
+            // A prompt is decoded first (`\\` → `\`), then expanded with
+            // `promptvars` (bash's default), which drops that backslash and
+            // keeps the character. A bare `\$` would decode to `#` for root.
+            Markup::Bash => text
+                .replace('\\', "\\\\\\\\")
+                .replace('$', "\\\\$")
+                .replace('`', "\\\\`"),
+            Markup::Pango => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&apos;"),
+        }
+    }
+
+    /// Render one segment
+    fn segment(self, segment: &ResolvedSegment) -> String {
+        let colors = &segment.config.colors;
+        let bold = segment.config.styles.text_bold;
+        let bg = colors.background.as_ref();
+
+        let mut parts = vec![
+            self.style(&segment.icon, colors.icon.as_ref(), None, false),
+            self.style(&segment.data.primary, colors.text.as_ref(), None, bold),
+        ];
+        if !segment.data.secondary.is_empty() {
+            parts.push(self.style(&segment.data.secondary, colors.text.as_ref(), None, bold));
+        }
+
+        // With a background the segment is padded into a solid block
+        let body = parts.join(" ");
+        if bg.is_some() {
+            self.wrap(format!(" {} ", body), None, bg, false)
+        } else {
+            body
+        }
+    }
+
+    /// Separator between two segments. A powerline arrow takes the previous
+    /// segment's background as its color and the next one's as background.
+    fn separator(
+        self,
+        separator: &str,
+        prev_bg: Option<&AnsiColor>,
+        curr_bg: Option<&AnsiColor>,
+    ) -> String {
+        if separator == POWERLINE_ARROW {
+            self.style(POWERLINE_ARROW, prev_bg, curr_bg, false)
+        } else {
+            self.style(separator, Some(&SEPARATOR_COLOR), None, false)
+        }
+    }
+
+    /// Render all segments as a single line of markup
+    pub fn render(self, separator: &str, segments: &[ResolvedSegment]) -> String {
+        let mut out = String::new();
+        for (i, segment) in segments.iter().enumerate() {
+            if i > 0 {
+                let prev_bg = segments[i - 1].background();
+                out.push_str(&self.separator(separator, prev_bg, segment.background()));
+            }
+            out.push_str(&self.segment(segment));
+        }
+        out
+    }
+}
+
+/// One block of the i3bar protocol
+#[derive(Debug, Serialize)]
+pub struct I3barBlock {
+    pub full_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub markup: &'static str,
+    pub separator: bool,
+    pub separator_block_width: u32,
+}
+
+/// Render segments as an i3bar status line: a JSON array of blocks, one per
+/// segment plus one per separator. Print one array per update after the
+/// protocol header.
+pub fn render_i3bar(separator: &str, segments: &[ResolvedSegment]) -> String {
+    let block = |full_text: String, name: Option<String>| I3barBlock {
+        full_text,
+        name,
+        markup: "pango",
+        separator: false,
+        separator_block_width: 0,
+    };
+
+    let mut blocks = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        if i > 0 {
+            let prev_bg = segments[i - 1].background();
+            let text = Markup::Pango.separator(separator, prev_bg, segment.background());
+            blocks.push(block(text, None));
+        }
+        let name = segment
+            .config
+            .custom_name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:?}", segment.config.id).to_lowercase());
+        blocks.push(block(Markup::Pango.segment(segment), Some(name)));
+    }
+
+    serde_json::to_string(&blocks).unwrap_or_default()
+}
+
+/// Output of a waybar custom module with `"return-type": "json"`
+#[derive(Debug, Serialize)]
+pub struct WaybarOutput {
+    pub text: String,
+    pub tooltip: String,
+    pub class: &'static str,
+}
+
+/// Render segments for a waybar custom module. The text uses Pango markup;
+/// the tooltip lists each segment on its own line, escaped for Pango.
+pub fn render_waybar(separator: &str, segments: &[ResolvedSegment]) -> String {
+    let tooltip = segments
+        .iter()
+        .map(|segment| {
+            let mut line = format!("{} {}", segment.icon, segment.data.primary);
+            if !segment.data.secondary.is_empty() {
+                line.push(' ');
+                line.push_str(&segment.data.secondary);
+            }
+            Markup::Pango.escape(&line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let output = WaybarOutput {
+        text: Markup::Pango.render(separator, segments),
+        tooltip,
+        class: "micusubcodeline",
+    };
+    serde_json::to_string(&output).unwrap_or_default()
+}
+
+fn tmux_color(color: &AnsiColor) -> String {
+    match color {
+        AnsiColor::Color16 { c16 } => format!("colour{}", c16),
+        AnsiColor::Color256 { c256 } => format!("colour{}", c256),
+        AnsiColor::Rgb { .. } => color.to_hex(),
+    }
+}
+
+fn zsh_color(color: &AnsiColor) -> String {
+    match color {
+        AnsiColor::Color16 { c16 } => c16.to_string(),
+        AnsiColor::Color256 { c256 } => c256.to_string(),
+        AnsiColor::Rgb { .. } => color.to_hex(),
+    }
+}
+
+/// SGR parameters for a foreground or background color
+fn sgr_color(color: &AnsiColor, background: bool) -> String {
+    let (base, bright_base, extended) = if background {
+        (40, 100, 48)
+    } else {
+        (30, 90, 38)
+    };
+    match color {
+        AnsiColor::Color16 { c16 } if *c16 < 8 => (base + *c16 as u32).to_string(),
+        AnsiColor::Color16 { c16 } => (bright_base + (*c16 as u32 - 8)).to_string(),
+        AnsiColor::Color256 { c256 } => format!("{};5;{}", extended, c256),
+        AnsiColor::Rgb { r, g, b } => format!("{};2;{};{};{}", extended, r, g, b),
+    }
+}
@@ -0,0 +1,141 @@
+use crate::config::{paths, InputData, SegmentConfig, SegmentId};
+use crate::core::segments::SegmentData;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Cached values older than this are never rendered, even as a fallback,
+/// and are dropped when the cache is saved
+const MAX_STALE_SECS: u64 = 3600;
+
+/// An unchanged value has its timestamp renewed (and the file rewritten)
+/// once it is this old, so it doesn't expire while it keeps being collected
+const REFRESH_AFTER_SECS: u64 = MAX_STALE_SECS / 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedSegment {
+    data: SegmentData,
+    cached_at: u64,
+}
+
+/// Last successfully collected data for each segment, persisted between
+/// invocations so a segment that misses its time budget can still render.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SegmentCache {
+    entries: HashMap<String, CachedSegment>,
+    /// Keys inserted or removed by this process, applied over the file's
+    /// current contents on save
+    #[serde(skip)]
+    changed: HashSet<String>,
+}
+
+impl SegmentCache {
+    /// Load the cache file, falling back to an empty cache on any error
+    pub fn load() -> Self {
+        Self::get_cache_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Persist the cache if anything changed since it was loaded. The file
+    /// is re-read first so entries written meanwhile by other statusline
+    /// processes (other workspaces) are kept, and expired entries are
+    /// dropped.
+    pub fn save(&self) {
+        if self.changed.is_empty() {
+            return;
+        }
+
+        let Some(cache_path) = Self::get_cache_path() else {
+            return;
+        };
+        if let Some(parent) = cache_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let mut merged = Self::load();
+        for key in &self.changed {
+            match self.entries.get(key) {
+                Some(entry) => merged.entries.insert(key.clone(), entry.clone()),
+                None => merged.entries.remove(key),
+            };
+        }
+        let now = now_secs();
+        merged
+            .entries
+            .retain(|_, entry| now.saturating_sub(entry.cached_at) <= MAX_STALE_SECS);
+
+        // Write to a temp file first so concurrent statusline processes never
+        // observe a half-written cache
+        if let Ok(json) = serde_json::to_string(&merged) {
+            let tmp_path = cache_path.with_extension(format!("json.{}", std::process::id()));
+            if std::fs::write(&tmp_path, json).is_ok()
+                && std::fs::rename(&tmp_path, &cache_path).is_err()
+            {
+                let _ = std::fs::remove_file(&tmp_path);
+            }
+        }
+    }
+
+    /// Get the last cached data for a key, if it is not too old to be useful
+    pub fn get(&self, key: &str) -> Option<&SegmentData> {
+        let entry = self.entries.get(key)?;
+        if now_secs().saturating_sub(entry.cached_at) > MAX_STALE_SECS {
+            return None;
+        }
+        Some(&entry.data)
+    }
+
+    /// Record fresh data. Only a changed value, or an unchanged one about to
+    /// expire, makes the cache file be rewritten.
+    pub fn insert(&mut self, key: String, data: SegmentData) {
+        let now = now_secs();
+        let unchanged = self.entries.get(&key).is_some_and(|entry| {
+            entry.data == data && now.saturating_sub(entry.cached_at) < REFRESH_AFTER_SECS
+        });
+        if unchanged {
+            return;
+        }
+        self.entries.insert(
+            key.clone(),
+            CachedSegment {
+                data,
+                cached_at: now,
+            },
+        );
+        self.changed.insert(key);
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if self.entries.remove(key).is_some() {
+            self.changed.insert(key.to_string());
+        }
+    }
+
+    /// Build the cache key for a segment. Segment output depends on the
+    /// workspace, so the current directory is part of the key. Custom
+    /// segments share an ID and are told apart by their name.
+    pub fn key_for(config: &SegmentConfig, input: &InputData) -> String {
+        match config.id {
+            SegmentId::Custom => format!(
+                "Custom:{}@{}",
+                config.custom_name().unwrap_or_default(),
+                input.workspace.current_dir
+            ),
+            _ => format!("{:?}@{}", config.id, input.workspace.current_dir),
+        }
+    }
+
+    fn get_cache_path() -> Option<PathBuf> {
+        Some(paths::cache_dir()?.join(".segment_cache.json"))
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
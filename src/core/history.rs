@@ -0,0 +1,481 @@
+//! Local history of session cost and usage.
+//!
+//! Every statusline invocation appends a record to `history.jsonl` unless
+//! nothing meaningful changed since the session's previous record. Values
+//! are cumulative per session, so reports attribute the difference between
+//! consecutive records to the record's day, project and model.
+
+use crate::config::{paths, InputData, SegmentConfig, SegmentId};
+use crate::core::segments::SegmentData;
+use crate::core::transcript;
+use chrono::{Local, TimeZone};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "history.jsonl";
+const STATE_FILE: &str = ".history_state.json";
+
+/// Sessions idle for longer than this are dropped from the dedup state
+const STATE_RETENTION_SECS: u64 = 7 * 24 * 3600;
+
+/// One snapshot of a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub timestamp: u64,
+    pub session_id: String,
+    pub project_dir: String,
+    pub model_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines_added: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines_removed: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_daily_usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_weekly_usd: Option<f64>,
+}
+
+impl HistoryRecord {
+    /// Build a record from the statusline input and the collected segments.
+    /// Context tokens and subscription spend come from their segments when
+    /// enabled; otherwise tokens are read from the transcript and
+    /// subscription data from the input, without any network request.
+    pub fn from_input(input: &InputData, segments: &[(SegmentConfig, SegmentData)]) -> Self {
+        let metadata = |id: SegmentId, key: &str| {
+            segments
+                .iter()
+                .find(|(config, _)| config.id == id)
+                .and_then(|(_, data)| data.metadata.get(key))
+        };
+
+        let context_tokens = metadata(SegmentId::ContextWindow, "tokens")
+            .and_then(|value| value.parse().ok())
+            .or_else(|| {
+                transcript::latest_usage(Path::new(&input.transcript_path))
+                    .map(|usage| usage.display_tokens())
+            });
+        let subscription_daily_usd = metadata(SegmentId::Subscription, "daily_cost")
+            .and_then(|value| value.parse().ok())
+            .or_else(|| input.subscription.as_ref().map(|s| s.daily_used_usd));
+        let subscription_weekly_usd = metadata(SegmentId::Subscription, "weekly_cost")
+            .and_then(|value| value.parse().ok())
+            .or_else(|| input.subscription.as_ref().map(|s| s.weekly_used_usd));
+
+        let cost = input.cost.as_ref();
+        Self {
+            timestamp: now_secs(),
+            session_id: session_id(&input.transcript_path),
+            project_dir: input.workspace.current_dir.clone(),
+            model_id: input.model.id.clone(),
+            cost_usd: cost.and_then(|c| c.total_cost_usd),
+            duration_ms: cost.and_then(|c| c.total_duration_ms),
+            lines_added: cost.and_then(|c| c.total_lines_added),
+            lines_removed: cost.and_then(|c| c.total_lines_removed),
+            context_tokens,
+            subscription_daily_usd,
+            subscription_weekly_usd,
+        }
+    }
+
+    /// Fingerprint of the fields that make a record worth keeping. Time
+    /// and duration advance on every invocation and are left out.
+    fn fingerprint(&self) -> String {
+        format!(
+            "{}|{}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}",
+            self.model_id,
+            self.project_dir,
+            self.cost_usd,
+            self.lines_added,
+            self.lines_removed,
+            self.context_tokens,
+            self.subscription_daily_usd,
+            self.subscription_weekly_usd
+        )
+    }
+}
+
+/// Session id is the transcript's file name without extension
+fn session_id(transcript_path: &str) -> String {
+    Path::new(transcript_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionState {
+    fingerprint: String,
+    last_seen: u64,
+}
+
+/// Last fingerprint per session, used to skip unchanged records
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryState {
+    sessions: HashMap<String, SessionState>,
+}
+
+pub struct HistoryStore;
+
+impl HistoryStore {
+    /// Append a record unless it matches the session's previous one
+    pub fn record(record: &HistoryRecord) {
+        if record.session_id.is_empty() {
+            return;
+        }
+        let (Some(history_path), Some(state_path)) =
+            (get_data_path(HISTORY_FILE), get_data_path(STATE_FILE))
+        else {
+            return;
+        };
+
+        let mut state: HistoryState = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        let fingerprint = record.fingerprint();
+        if state
+            .sessions
+            .get(&record.session_id)
+            .is_some_and(|s| s.fingerprint == fingerprint)
+        {
+            return;
+        }
+
+        if let Some(parent) = history_path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let Ok(line) = serde_json::to_string(record) else {
+            return;
+        };
+        let appended = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&history_path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if appended.is_err() {
+            return;
+        }
+
+        state.sessions.insert(
+            record.session_id.clone(),
+            SessionState {
+                fingerprint,
+                last_seen: record.timestamp,
+            },
+        );
+        state
+            .sessions
+            .retain(|_, s| record.timestamp.saturating_sub(s.last_seen) < STATE_RETENTION_SECS);
+
+        if let Ok(json) = serde_json::to_string(&state) {
+            let tmp_path = state_path.with_extension(format!("json.{}", std::process::id()));
+            if fs::write(&tmp_path, json).is_ok() && fs::rename(&tmp_path, &state_path).is_err() {
+                let _ = fs::remove_file(&tmp_path);
+            }
+        }
+    }
+
+    /// Read every record, skipping lines that fail to parse
+    pub fn load() -> Vec<HistoryRecord> {
+        let Some(file) = get_data_path(HISTORY_FILE).and_then(|path| fs::File::open(path).ok())
+        else {
+            return Vec::new();
+        };
+        BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect()
+    }
+
+    pub fn history_path() -> Option<PathBuf> {
+        get_data_path(HISTORY_FILE)
+    }
+}
+
+/// What a report row groups by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportGroup {
+    Day,
+    Project,
+    Model,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Csv,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ReportRow {
+    pub key: String,
+    pub sessions: usize,
+    pub cost_usd: f64,
+    pub duration_ms: u64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+    pub max_context_tokens: u32,
+    /// Highest subscription daily spend seen (only meaningful per day)
+    pub subscription_usd: Option<f64>,
+}
+
+/// Summarize records. Each record contributes the increase of the
+/// session's cumulative values since its previous record; a value that went
+/// down means the counter was reset and counts in full. Only records at or
+/// after `since` (unix seconds) are included.
+pub fn build_report(
+    records: &[HistoryRecord],
+    group: ReportGroup,
+    since: Option<u64>,
+) -> Vec<ReportRow> {
+    let mut ordered: Vec<&HistoryRecord> = records.iter().collect();
+    ordered.sort_by(|a, b| {
+        a.session_id
+            .cmp(&b.session_id)
+            .then(a.timestamp.cmp(&b.timestamp))
+    });
+
+    let mut rows: BTreeMap<String, ReportRow> = BTreeMap::new();
+    let mut sessions: HashMap<String, HashSet<&str>> = HashMap::new();
+    let mut previous: Option<&HistoryRecord> = None;
+
+    for record in ordered {
+        let prev = previous.filter(|p| p.session_id == record.session_id);
+        previous = Some(record);
+
+        if since.is_some_and(|since| record.timestamp < since) {
+            continue;
+        }
+
+        let key = match group {
+            ReportGroup::Day => Local
+                .timestamp_opt(record.timestamp as i64, 0)
+                .single()
+                .map(|time| time.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            ReportGroup::Project => record.project_dir.clone(),
+            ReportGroup::Model => record.model_id.clone(),
+        };
+
+        let row = rows.entry(key.clone()).or_insert_with(|| ReportRow {
+            key: key.clone(),
+            ..Default::default()
+        });
+        sessions
+            .entry(key)
+            .or_default()
+            .insert(record.session_id.as_str());
+
+        row.cost_usd += delta_f64(record.cost_usd, prev.and_then(|p| p.cost_usd));
+        row.duration_ms += delta_u64(record.duration_ms, prev.and_then(|p| p.duration_ms));
+        row.lines_added += delta_u64(
+            record.lines_added.map(u64::from),
+            prev.and_then(|p| p.lines_added).map(u64::from),
+        );
+        row.lines_removed += delta_u64(
+            record.lines_removed.map(u64::from),
+            prev.and_then(|p| p.lines_removed).map(u64::from),
+        );
+        row.max_context_tokens = row
+            .max_context_tokens
+            .max(record.context_tokens.unwrap_or(0));
+        if let Some(daily) = record.subscription_daily_usd {
+            row.subscription_usd = Some(row.subscription_usd.map_or(daily, |s| s.max(daily)));
+        }
+    }
+
+    rows.into_values()
+        .map(|mut row| {
+            row.sessions = sessions.get(&row.key).map_or(0, |s| s.len());
+            row
+        })
+        .collect()
+}
+
+fn delta_f64(current: Option<f64>, previous: Option<f64>) -> f64 {
+    match (current, previous) {
+        (Some(current), Some(previous)) if current >= previous => current - previous,
+        (Some(current), _) => current,
+        (None, _) => 0.0,
+    }
+}
+
+fn delta_u64(current: Option<u64>, previous: Option<u64>) -> u64 {
+    match (current, previous) {
+        (Some(current), Some(previous)) if current >= previous => current - previous,
+        (Some(current), _) => current,
+        (None, _) => 0,
+    }
+}
+
+/// Render report rows as an aligned table (with a total line) or CSV
+pub fn format_report(rows: &[ReportRow], group: ReportGroup, format: ReportFormat) -> String {
+    let group_name = match group {
+        ReportGroup::Day => "day",
+        ReportGroup::Project => "project",
+        ReportGroup::Model => "model",
+    };
+    let show_subscription = group == ReportGroup::Day;
+
+    match format {
+        ReportFormat::Csv => {
+            let mut out = format!(
+                "{},sessions,cost_usd,duration_ms,lines_added,lines_removed,max_context_tokens{}\n",
+                group_name,
+                if show_subscription {
+                    ",subscription_daily_usd"
+                } else {
+                    ""
+                }
+            );
+            for row in rows {
+                out.push_str(&format!(
+                    "{},{},{:.4},{},{},{},{}",
+                    csv_field(&row.key),
+                    row.sessions,
+                    row.cost_usd,
+                    row.duration_ms,
+                    row.lines_added,
+                    row.lines_removed,
+                    row.max_context_tokens
+                ));
+                if show_subscription {
+                    out.push(',');
+                    if let Some(sub) = row.subscription_usd {
+                        out.push_str(&format!("{:.4}", sub));
+                    }
+                }
+                out.push('\n');
+            }
+            out
+        }
+        ReportFormat::Text => {
+            let mut header = vec![
+                group_name.to_string(),
+                "sessions".to_string(),
+                "cost".to_string(),
+                "duration".to_string(),
+                "lines".to_string(),
+                "max context".to_string(),
+            ];
+            if show_subscription {
+                header.push("subscription".to_string());
+            }
+
+            let to_cells = |row: &ReportRow| {
+                let mut cells = vec![
+                    row.key.clone(),
+                    row.sessions.to_string(),
+                    format!("${:.2}", row.cost_usd),
+                    format_duration(row.duration_ms),
+                    format!("+{} -{}", row.lines_added, row.lines_removed),
+                    row.max_context_tokens.to_string(),
+                ];
+                if show_subscription {
+                    cells.push(
+                        row.subscription_usd
+                            .map(|s| format!("${:.2}", s))
+                            .unwrap_or_else(|| "-".to_string()),
+                    );
+                }
+                cells
+            };
+
+            let total = ReportRow {
+                key: "total".to_string(),
+                sessions: 0,
+                cost_usd: rows.iter().map(|r| r.cost_usd).sum(),
+                duration_ms: rows.iter().map(|r| r.duration_ms).sum(),
+                lines_added: rows.iter().map(|r| r.lines_added).sum(),
+                lines_removed: rows.iter().map(|r| r.lines_removed).sum(),
+                max_context_tokens: rows.iter().map(|r| r.max_context_tokens).max().unwrap_or(0),
+                subscription_usd: None,
+            };
+            let mut total_cells = to_cells(&total);
+            total_cells[1] = String::new();
+            if show_subscription {
+                total_cells[6] = String::new();
+            }
+
+            let mut table = vec![header];
+            table.extend(rows.iter().map(to_cells));
+            table.push(total_cells);
+
+            let widths: Vec<usize> = (0..table[0].len())
+                .map(|col| {
+                    table
+                        .iter()
+                        .map(|cells| unicode_width::UnicodeWidthStr::width(cells[col].as_str()))
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+
+            let mut out = String::new();
+            for (i, cells) in table.iter().enumerate() {
+                if i == table.len() - 1 {
+                    let rule_width = widths.iter().sum::<usize>() + 2 * (widths.len() - 1);
+                    out.push_str(&"-".repeat(rule_width));
+                    out.push('\n');
+                }
+                let line: Vec<String> = cells
+                    .iter()
+                    .zip(&widths)
+                    .enumerate()
+                    .map(|(col, (cell, width))| {
+                        let pad = width - unicode_width::UnicodeWidthStr::width(cell.as_str());
+                        if col == 0 {
+                            format!("{}{}", cell, " ".repeat(pad))
+                        } else {
+                            format!("{}{}", " ".repeat(pad), cell)
+                        }
+                    })
+                    .collect();
+                out.push_str(line.join("  ").trim_end());
+                out.push('\n');
+            }
+            out
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_duration(ms: u64) -> String {
+    let minutes = ms / 60_000;
+    if minutes >= 60 {
+        format!("{}h{:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+fn get_data_path(file: &str) -> Option<PathBuf> {
+    Some(paths::data_dir()?.join(file))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
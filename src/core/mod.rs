@@ -1,4 +1,9 @@
+pub mod backends;
+pub mod cache;
+pub mod history;
 pub mod segments;
 pub mod statusline;
+pub mod template;
+pub mod transcript;
 
 pub use statusline::{collect_all_segments, StatusLineGenerator};
@@ -1,31 +1,77 @@
 use super::{Segment, SegmentData};
-use crate::config::{InputData, ModelConfig, SegmentId, TranscriptEntry};
+use crate::config::schema::{OptionDefault, OptionKind, OptionSpec};
+use crate::config::{InputData, ModelConfig, SegmentId};
+use crate::core::{template, transcript};
 use std::collections::HashMap;
-use std::fs;
-use std::io::{BufRead, BufReader};
-use std::path::{Path, PathBuf};
+use std::path::Path;
+
+/// Default text template (see `core::template`), overridable via the `format` option
+const DEFAULT_FORMAT: &str = "{pct} · {tokens_short} tokens";
+
+/// Secondary text parts, enabled with the `breakdown` / `headroom` options
+const BREAKDOWN_FORMAT: &str =
+    "R {cache_read_short} W {cache_creation_short} I {input_short} O {output_short}";
+const HEADROOM_FORMAT: &str = "{headroom_short} to compact{?turns_left: (~{turns_left} turns)}";
+
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "breakdown",
+        kind: OptionKind::Bool,
+        default: OptionDefault::Bool(false),
+        description: "Show cache read, cache creation, input and output tokens",
+    },
+    OptionSpec {
+        name: "headroom",
+        kind: OptionKind::Bool,
+        default: OptionDefault::Bool(false),
+        description: "Show tokens left before auto-compact",
+    },
+];
 
 #[derive(Default)]
-pub struct ContextWindowSegment;
+pub struct ContextWindowSegment {
+    show_breakdown: bool,
+    show_headroom: bool,
+}
 
 impl ContextWindowSegment {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn with_breakdown(mut self, show_breakdown: bool) -> Self {
+        self.show_breakdown = show_breakdown;
+        self
+    }
+
+    pub fn with_headroom(mut self, show_headroom: bool) -> Self {
+        self.show_headroom = show_headroom;
+        self
     }
 
-    /// Get context limit for the specified model
-    fn get_context_limit_for_model(model_id: &str) -> u32 {
-        let model_config = ModelConfig::load();
-        model_config.get_context_limit(model_id)
+    /// Format a token count like `156.4k`
+    fn short_tokens(tokens: u32) -> String {
+        if tokens >= 1000 {
+            let k_value = tokens as f64 / 1000.0;
+            if k_value.fract() == 0.0 {
+                format!("{}k", k_value as u32)
+            } else {
+                format!("{:.1}k", k_value)
+            }
+        } else {
+            tokens.to_string()
+        }
     }
 }
 
 impl Segment for ContextWindowSegment {
     fn collect(&self, input: &InputData) -> Option<SegmentData> {
         // Dynamically determine context limit based on current model ID
-        let context_limit = Self::get_context_limit_for_model(&input.model.id);
+        let model_config = ModelConfig::load_for_workspace(&input.workspace.current_dir);
+        let context_limit = model_config.get_context_limit(&input.model.id);
 
-        let context_used_token_opt = parse_transcript_usage(&input.transcript_path);
+        let usage = transcript::latest_usage(Path::new(&input.transcript_path));
+        let context_used_token_opt = usage.as_ref().map(|usage| usage.display_tokens());
 
         let (percentage_display, tokens_display) = match context_used_token_opt {
             Some(context_used_token) => {
@@ -37,18 +83,7 @@ impl Segment for ContextWindowSegment {
                     format!("{:.1}%", context_used_rate)
                 };
 
-                let tokens = if context_used_token >= 1000 {
-                    let k_value = context_used_token as f64 / 1000.0;
-                    if k_value.fract() == 0.0 {
-                        format!("{}k", k_value as u32)
-                    } else {
-                        format!("{:.1}k", k_value)
-                    }
-                } else {
-                    context_used_token.to_string()
-                };
-
-                (percentage, tokens)
+                (percentage, Self::short_tokens(context_used_token))
             }
             None => {
                 // No usage data available
@@ -68,205 +103,61 @@ impl Segment for ContextWindowSegment {
                 metadata.insert("percentage".to_string(), "-".to_string());
             }
         }
+        metadata.insert("pct".to_string(), percentage_display);
+        metadata.insert("tokens_short".to_string(), tokens_display);
         metadata.insert("limit".to_string(), context_limit.to_string());
         metadata.insert("model".to_string(), input.model.id.clone());
 
-        Some(SegmentData {
-            primary: format!("{} · {} tokens", percentage_display, tokens_display),
-            secondary: String::new(),
-            metadata,
-        })
-    }
-
-    fn id(&self) -> SegmentId {
-        SegmentId::ContextWindow
-    }
-}
-
-fn parse_transcript_usage<P: AsRef<Path>>(transcript_path: P) -> Option<u32> {
-    let path = transcript_path.as_ref();
-
-    // Try to parse from current transcript file
-    if let Some(usage) = try_parse_transcript_file(path) {
-        return Some(usage);
-    }
-
-    // If file doesn't exist, try to find usage from project history
-    if !path.exists() {
-        if let Some(usage) = try_find_usage_from_project_history(path) {
-            return Some(usage);
-        }
-    }
-
-    None
-}
-
-fn try_parse_transcript_file(path: &Path) -> Option<u32> {
-    let file = fs::File::open(path).ok()?;
-    let reader = BufReader::new(file);
-    let lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap_or_default();
-
-    if lines.is_empty() {
-        return None;
-    }
-
-    // Check if the last line is a summary
-    let last_line = lines.last()?.trim();
-    if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(last_line) {
-        if entry.r#type.as_deref() == Some("summary") {
-            // Handle summary case: find usage by leafUuid
-            if let Some(leaf_uuid) = &entry.leaf_uuid {
-                let project_dir = path.parent()?;
-                return find_usage_by_leaf_uuid(leaf_uuid, project_dir);
+        if let (Some(usage), Some(context_used_token)) = (&usage, context_used_token_opt) {
+            // Where the context tokens of the last turn came from
+            let parts = [
+                ("cache_read", usage.cache_read_input_tokens),
+                ("cache_creation", usage.cache_creation_input_tokens),
+                ("input", usage.input_tokens),
+                ("output", usage.output_tokens),
+            ];
+            for (key, tokens) in parts {
+                metadata.insert(key.to_string(), tokens.to_string());
+                metadata.insert(format!("{}_short", key), Self::short_tokens(tokens));
             }
-        }
-    }
-
-    // Normal case: find the last assistant message in current file
-    for line in lines.iter().rev() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-
-        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) {
-            if entry.r#type.as_deref() == Some("assistant") {
-                if let Some(message) = &entry.message {
-                    if let Some(raw_usage) = &message.usage {
-                        let normalized = raw_usage.clone().normalize();
-                        return Some(normalized.display_tokens());
-                    }
-                }
-            }
-        }
-    }
-
-    None
-}
-
-fn find_usage_by_leaf_uuid(leaf_uuid: &str, project_dir: &Path) -> Option<u32> {
-    // Search for the leafUuid across all session files in the project directory
-    let entries = fs::read_dir(project_dir).ok()?;
-
-    for entry in entries {
-        let entry = entry.ok()?;
-        let path = entry.path();
-
-        if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
-            continue;
-        }
-
-        if let Some(usage) = search_uuid_in_file(&path, leaf_uuid) {
-            return Some(usage);
-        }
-    }
 
-    None
-}
-
-fn search_uuid_in_file(path: &Path, target_uuid: &str) -> Option<u32> {
-    let file = fs::File::open(path).ok()?;
-    let reader = BufReader::new(file);
-    let lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap_or_default();
-
-    // Find the message with target_uuid
-    for line in &lines {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-
-        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) {
-            if let Some(uuid) = &entry.uuid {
-                if uuid == target_uuid {
-                    // Found the target message, check its type
-                    if entry.r#type.as_deref() == Some("assistant") {
-                        // Direct assistant message with usage
-                        if let Some(message) = &entry.message {
-                            if let Some(raw_usage) = &message.usage {
-                                let normalized = raw_usage.clone().normalize();
-                                return Some(normalized.display_tokens());
-                            }
-                        }
-                    } else if entry.r#type.as_deref() == Some("user") {
-                        // User message, need to find the parent assistant message
-                        if let Some(parent_uuid) = &entry.parent_uuid {
-                            return find_assistant_message_by_uuid(&lines, parent_uuid);
-                        }
-                    }
-                    break;
-                }
+            // Room left before Claude Code compacts the conversation, and
+            // roughly how many turns like the last one fit in it
+            let threshold =
+                context_limit.saturating_sub(model_config.get_compact_reserve(&input.model.id));
+            let headroom = threshold.saturating_sub(context_used_token);
+            metadata.insert("compact_threshold".to_string(), threshold.to_string());
+            metadata.insert("headroom".to_string(), headroom.to_string());
+            metadata.insert("headroom_short".to_string(), Self::short_tokens(headroom));
+
+            let turn_tokens =
+                usage.input_tokens + usage.cache_creation_input_tokens + usage.output_tokens;
+            if let Some(turns_left) = headroom.checked_div(turn_tokens) {
+                metadata.insert("turns_left".to_string(), turns_left.to_string());
             }
         }
-    }
-
-    None
-}
-
-fn find_assistant_message_by_uuid(lines: &[String], target_uuid: &str) -> Option<u32> {
-    for line in lines {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
 
-        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(line) {
-            if let Some(uuid) = &entry.uuid {
-                if uuid == target_uuid && entry.r#type.as_deref() == Some("assistant") {
-                    if let Some(message) = &entry.message {
-                        if let Some(raw_usage) = &message.usage {
-                            let normalized = raw_usage.clone().normalize();
-                            return Some(normalized.display_tokens());
-                        }
-                    }
-                }
-            }
+        let mut secondary_parts = Vec::new();
+        if self.show_breakdown && metadata.contains_key("cache_read") {
+            secondary_parts.push(template::render(BREAKDOWN_FORMAT, &metadata));
         }
-    }
-
-    None
-}
-
-fn try_find_usage_from_project_history(transcript_path: &Path) -> Option<u32> {
-    let project_dir = transcript_path.parent()?;
-
-    // Find the most recent session file in the project directory
-    let mut session_files: Vec<PathBuf> = Vec::new();
-    let entries = fs::read_dir(project_dir).ok()?;
-
-    for entry in entries {
-        let entry = entry.ok()?;
-        let path = entry.path();
-
-        if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-            session_files.push(path);
+        if self.show_headroom && metadata.contains_key("headroom") {
+            secondary_parts.push(template::render(HEADROOM_FORMAT, &metadata));
         }
-    }
+        let secondary = if secondary_parts.is_empty() {
+            String::new()
+        } else {
+            format!("· {}", secondary_parts.join(" · "))
+        };
 
-    if session_files.is_empty() {
-        return None;
+        Some(SegmentData {
+            primary: template::render(DEFAULT_FORMAT, &metadata),
+            secondary,
+            metadata,
+        })
     }
 
-    // Sort by modification time (most recent first)
-    session_files.sort_by_key(|path| {
-        fs::metadata(path)
-            .and_then(|m| m.modified())
-            .unwrap_or(std::time::UNIX_EPOCH)
-    });
-    session_files.reverse();
-
-    // Try to find usage from the most recent session
-    for session_path in &session_files {
-        if let Some(usage) = try_parse_transcript_file(session_path) {
-            return Some(usage);
-        }
+    fn id(&self) -> SegmentId {
+        SegmentId::ContextWindow
     }
-
-    None
 }
@@ -1,42 +1,152 @@
 use super::{Segment, SegmentData};
-use crate::config::{InputData, SegmentId};
+use crate::config::schema::{OptionDefault, OptionKind, OptionSpec};
+use crate::config::{InputData, ModelConfig, SegmentId};
+use crate::core::template;
+use crate::core::transcript::{self, TokenTotals};
 use std::collections::HashMap;
+use std::path::Path;
+
+/// Default text template (see `core::template`), overridable via the `format` option
+const DEFAULT_FORMAT: &str = "{cost_display}";
+
+pub const OPTIONS: &[OptionSpec] = &[OptionSpec {
+    name: "estimate",
+    kind: OptionKind::Enum(&["missing", "always", "never"]),
+    default: OptionDefault::Str("missing"),
+    description: "When to estimate cost from transcript tokens",
+}];
+
+/// When to estimate cost from transcript tokens, selected with the
+/// `estimate` option
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CostEstimate {
+    /// Only when Claude Code doesn't report a cost
+    #[default]
+    Missing,
+    /// Always, ignoring the reported cost (e.g. behind a relay)
+    Always,
+    /// Never; the reported cost is shown as is and the segment is hidden
+    /// without one
+    Never,
+}
+
+impl CostEstimate {
+    pub fn from_option(value: Option<&str>) -> Self {
+        match value {
+            Some("always") => CostEstimate::Always,
+            Some("never") => CostEstimate::Never,
+            _ => CostEstimate::Missing,
+        }
+    }
+}
 
 #[derive(Default)]
-pub struct CostSegment;
+pub struct CostSegment {
+    estimate: CostEstimate,
+}
 
 impl CostSegment {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn with_estimate(mut self, estimate: CostEstimate) -> Self {
+        self.estimate = estimate;
+        self
     }
+
+    /// Price every model's tokens in the session transcript. Tokens of
+    /// models without known prices are left out and the estimate is marked
+    /// partial; `None` if nothing could be priced.
+    fn estimate_cost(input: &InputData, totals: &HashMap<String, TokenTotals>) -> Option<Estimate> {
+        let model_config = ModelConfig::load_for_workspace(&input.workspace.current_dir);
+
+        let mut cost = None;
+        let mut partial = false;
+        for (model, tokens) in totals {
+            // Messages without a model id ran on the session's model
+            let model_id = if model.is_empty() {
+                &input.model.id
+            } else {
+                model
+            };
+            if let Some(pricing) = model_config.get_pricing(model_id) {
+                *cost.get_or_insert(0.0) += pricing.cost(
+                    tokens.input,
+                    tokens.output,
+                    tokens.cache_creation,
+                    tokens.cache_read,
+                );
+            } else if !tokens.is_empty() {
+                partial = true;
+            }
+        }
+        cost.map(|cost| Estimate { cost, partial })
+    }
+}
+
+/// Cost estimated from transcript tokens
+struct Estimate {
+    cost: f64,
+    /// Some tokens belong to models without known prices
+    partial: bool,
 }
 
 impl Segment for CostSegment {
     fn collect(&self, input: &InputData) -> Option<SegmentData> {
-        let cost_data = input.cost.as_ref()?;
+        let reported = input.cost.as_ref().and_then(|cost| cost.total_cost_usd);
+        let needs_totals = match self.estimate {
+            CostEstimate::Always => true,
+            CostEstimate::Missing => reported.is_none_or(|c| c == 0.0),
+            CostEstimate::Never => false,
+        };
+        let totals = if needs_totals {
+            transcript::session_token_totals(Path::new(&input.transcript_path))
+        } else {
+            None
+        };
+        // Relays often report $0 for sessions that did use tokens; when an
+        // estimate may stand in, that is no report at all. With `never` no
+        // totals are read and a reported $0 is shown as is.
+        let reported = reported.filter(|&cost| {
+            cost > 0.0
+                || !totals
+                    .as_ref()
+                    .is_some_and(|t| t.values().any(|t| !t.is_empty()))
+        });
 
-        // Primary display: total cost
-        let primary = if let Some(cost) = cost_data.total_cost_usd {
-            if cost == 0.0 || cost < 0.01 {
-                "$0".to_string()
-            } else {
-                format!("${:.2}", cost)
+        let (cost, estimated, partial) = match (reported, self.estimate) {
+            (Some(cost), CostEstimate::Missing | CostEstimate::Never) => (cost, false, false),
+            (None, CostEstimate::Never) => return None,
+            _ => {
+                let estimate = Self::estimate_cost(input, totals.as_ref()?)?;
+                (estimate.cost, true, estimate.partial)
             }
-        } else {
-            return None;
         };
 
-        // Secondary display: empty for cost segment
-        let secondary = String::new();
+        let mut cost_display = if cost == 0.0 || cost < 0.01 {
+            "$0".to_string()
+        } else {
+            format!("${:.2}", cost)
+        };
+        // Estimates are marked so they can't be mistaken for billed cost
+        if estimated {
+            cost_display.insert(0, '~');
+        }
+        // ...and a trailing `+` when unpriced models make it a lower bound
+        if partial {
+            cost_display.push('+');
+        }
 
         let mut metadata = HashMap::new();
-        if let Some(cost) = cost_data.total_cost_usd {
-            metadata.insert("cost".to_string(), cost.to_string());
-        }
+        metadata.insert("cost".to_string(), cost.to_string());
+        metadata.insert("cost_display".to_string(), cost_display);
+        metadata.insert("estimated".to_string(), estimated.to_string());
+        metadata.insert("partial".to_string(), partial.to_string());
 
         Some(SegmentData {
-            primary,
-            secondary,
+            primary: template::render(DEFAULT_FORMAT, &metadata),
+            secondary: String::new(),
             metadata,
         })
     }
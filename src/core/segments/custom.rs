@@ -0,0 +1,403 @@
+use super::{Segment, SegmentData};
+use crate::config::schema::{OptionDefault, OptionKind, OptionSpec};
+use crate::config::{paths, InputData, SegmentId};
+use crate::utils::hash::fnv1a_64;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_TIMEOUT_MS: u64 = 500;
+const DEFAULT_CACHE_TTL_SECS: u64 = 10;
+
+/// Options read by `CustomSegment::from_options`
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "name",
+        kind: OptionKind::String,
+        default: OptionDefault::Str("custom"),
+        description: "Unique name of this custom segment",
+    },
+    OptionSpec {
+        name: "command",
+        kind: OptionKind::String,
+        default: OptionDefault::None,
+        description: "Shell command whose output is shown",
+    },
+    OptionSpec {
+        name: "cwd",
+        kind: OptionKind::String,
+        default: OptionDefault::Str("workspace"),
+        description: "Where the command runs: workspace, home or a path",
+    },
+    OptionSpec {
+        name: "timeout_ms",
+        kind: OptionKind::Integer {
+            min: 1,
+            max: 60_000,
+        },
+        default: OptionDefault::Integer(DEFAULT_TIMEOUT_MS),
+        description: "Time budget for the command",
+    },
+    OptionSpec {
+        name: "cache_ttl",
+        kind: OptionKind::Integer {
+            min: 0,
+            max: 86_400,
+        },
+        default: OptionDefault::Integer(DEFAULT_CACHE_TTL_SECS),
+        description: "Seconds the output is reused",
+    },
+    OptionSpec {
+        name: "output",
+        kind: OptionKind::Enum(&["text", "json"]),
+        default: OptionDefault::Str("text"),
+        description: "Plain lines, or a JSON object with primary/secondary/metadata",
+    },
+    OptionSpec {
+        name: "primary_key",
+        kind: OptionKind::String,
+        default: OptionDefault::Str("primary"),
+        description: "JSON key of the main text",
+    },
+    OptionSpec {
+        name: "secondary_key",
+        kind: OptionKind::String,
+        default: OptionDefault::Str("secondary"),
+        description: "JSON key of the secondary text",
+    },
+];
+
+/// Where the command runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkingDir {
+    /// Claude Code's current workspace directory
+    Workspace,
+    /// The user's home directory
+    Home,
+    /// A fixed path (`~` is expanded)
+    Path(String),
+}
+
+/// How the command's stdout is turned into segment data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// First line is primary, second line is secondary
+    Text,
+    /// A JSON object mapped to primary/secondary/metadata
+    Json,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CommandCache {
+    command: String,
+    cwd: String,
+    data: SegmentData,
+    cached_at: u64,
+}
+
+/// A user-defined segment that renders the output of a shell command
+pub struct CustomSegment {
+    name: String,
+    command: String,
+    working_dir: WorkingDir,
+    timeout: Duration,
+    cache_ttl: u64,
+    output: OutputFormat,
+    primary_key: String,
+    secondary_key: String,
+}
+
+impl CustomSegment {
+    pub fn new(name: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            command: command.into(),
+            working_dir: WorkingDir::Workspace,
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            cache_ttl: DEFAULT_CACHE_TTL_SECS,
+            output: OutputFormat::Text,
+            primary_key: "primary".to_string(),
+            secondary_key: "secondary".to_string(),
+        }
+    }
+
+    /// Build a custom segment from its `[segments.options]` table.
+    /// Returns None if no `command` is configured.
+    pub fn from_options(options: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let get_str = |key: &str| options.get(key).and_then(|v| v.as_str());
+
+        let command = get_str("command").filter(|c| !c.trim().is_empty())?;
+        let name = get_str("name").unwrap_or("custom");
+        let mut segment = Self::new(name, command);
+
+        if let Some(cwd) = get_str("cwd") {
+            segment.working_dir = match cwd {
+                "workspace" => WorkingDir::Workspace,
+                "home" => WorkingDir::Home,
+                path => WorkingDir::Path(path.to_string()),
+            };
+        }
+        if let Some(timeout_ms) = options.get("timeout_ms").and_then(|v| v.as_u64()) {
+            segment.timeout = Duration::from_millis(timeout_ms);
+        }
+        if let Some(cache_ttl) = options.get("cache_ttl").and_then(|v| v.as_u64()) {
+            segment.cache_ttl = cache_ttl;
+        }
+        if get_str("output") == Some("json") {
+            segment.output = OutputFormat::Json;
+        }
+        if let Some(key) = get_str("primary_key") {
+            segment.primary_key = key.to_string();
+        }
+        if let Some(key) = get_str("secondary_key") {
+            segment.secondary_key = key.to_string();
+        }
+
+        Some(segment)
+    }
+
+    fn resolve_working_dir(&self, input: &InputData) -> Option<PathBuf> {
+        match &self.working_dir {
+            WorkingDir::Workspace => Some(PathBuf::from(&input.workspace.current_dir)),
+            WorkingDir::Home => dirs::home_dir(),
+            WorkingDir::Path(path) => {
+                if let Some(rest) = path.strip_prefix("~/") {
+                    dirs::home_dir().map(|home| home.join(rest))
+                } else if path == "~" {
+                    dirs::home_dir()
+                } else {
+                    Some(PathBuf::from(path))
+                }
+            }
+        }
+    }
+
+    /// Run the command through the platform shell, killing it if it
+    /// exceeds the timeout. Returns stdout on a successful exit.
+    fn run_command(&self, cwd: &PathBuf, input: &InputData) -> Option<String> {
+        let mut command = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.args(["/C", &self.command]);
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.args(["-c", &self.command]);
+            c
+        };
+
+        // Run in its own process group so a timeout takes down everything
+        // the shell started, not just the shell
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        let mut child = command
+            .current_dir(cwd)
+            .env("MICU_WORKSPACE_DIR", &input.workspace.current_dir)
+            .env("MICU_MODEL_ID", &input.model.id)
+            .env("MICU_TRANSCRIPT_PATH", &input.transcript_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        // Drain stdout on a separate thread so a chatty command can't block
+        // on a full pipe while we wait for it
+        let mut stdout = child.stdout.take()?;
+        let reader = std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stdout.read_to_string(&mut buf);
+            buf
+        });
+
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if start.elapsed() < self.timeout => {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                _ => {
+                    kill_process_tree(&mut child);
+                    let _ = child.wait();
+                    return None;
+                }
+            }
+        };
+
+        let output = reader.join().ok()?;
+        if status.success() {
+            Some(output)
+        } else {
+            None
+        }
+    }
+
+    fn parse_output(&self, output: &str) -> Option<SegmentData> {
+        let mut metadata = HashMap::new();
+        metadata.insert("name".to_string(), self.name.clone());
+
+        match self.output {
+            OutputFormat::Text => {
+                let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+                let primary = lines.next()?.to_string();
+                let secondary = lines.next().unwrap_or("").to_string();
+
+                Some(SegmentData {
+                    primary,
+                    secondary,
+                    metadata,
+                })
+            }
+            OutputFormat::Json => {
+                let value: serde_json::Value = serde_json::from_str(output.trim()).ok()?;
+                let object = value.as_object()?;
+
+                // Every scalar field becomes metadata; a nested "metadata"
+                // object is flattened in as well
+                for (key, value) in object {
+                    if let Some(text) = json_scalar_to_string(value) {
+                        metadata.insert(key.clone(), text);
+                    }
+                }
+                if let Some(nested) = object.get("metadata").and_then(|m| m.as_object()) {
+                    for (key, value) in nested {
+                        if let Some(text) = json_scalar_to_string(value) {
+                            metadata.insert(key.clone(), text);
+                        }
+                    }
+                }
+
+                let primary = object
+                    .get(&self.primary_key)
+                    .and_then(json_scalar_to_string)
+                    .filter(|p| !p.is_empty())?;
+                let secondary = object
+                    .get(&self.secondary_key)
+                    .and_then(json_scalar_to_string)
+                    .unwrap_or_default();
+
+                Some(SegmentData {
+                    primary,
+                    secondary,
+                    metadata,
+                })
+            }
+        }
+    }
+
+    /// One cache file per (name, command, cwd), so segments with similar
+    /// names or the same command in several workspaces don't evict each other
+    fn get_cache_path(&self, cwd: &str) -> Option<PathBuf> {
+        let cache_dir = paths::cache_dir()?;
+        let key = [self.name.as_str(), self.command.as_str(), cwd].join("\0");
+        Some(
+            cache_dir
+                .join("custom_cache")
+                .join(format!("{:016x}.json", fnv1a_64(key.as_bytes()))),
+        )
+    }
+
+    fn load_cache(&self, cwd: &str) -> Option<SegmentData> {
+        let content = std::fs::read_to_string(self.get_cache_path(cwd)?).ok()?;
+        let cache: CommandCache = serde_json::from_str(&content).ok()?;
+
+        let fresh = now_secs().saturating_sub(cache.cached_at) < self.cache_ttl;
+        if fresh && cache.command == self.command && cache.cwd == cwd {
+            Some(cache.data)
+        } else {
+            None
+        }
+    }
+
+    fn save_cache(&self, cwd: &str, data: &SegmentData) {
+        let Some(cache_path) = self.get_cache_path(cwd) else {
+            return;
+        };
+        if let Some(parent) = cache_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let cache = CommandCache {
+            command: self.command.clone(),
+            cwd: cwd.to_string(),
+            data: data.clone(),
+            cached_at: now_secs(),
+        };
+        if let Ok(json) = serde_json::to_string_pretty(&cache) {
+            let tmp_path = cache_path.with_extension(format!("json.{}", std::process::id()));
+            if std::fs::write(&tmp_path, json).is_ok()
+                && std::fs::rename(&tmp_path, &cache_path).is_err()
+            {
+                let _ = std::fs::remove_file(&tmp_path);
+            }
+        }
+    }
+}
+
+impl Segment for CustomSegment {
+    fn collect(&self, input: &InputData) -> Option<SegmentData> {
+        let cwd = self.resolve_working_dir(input)?;
+        let cwd_key = cwd.to_string_lossy().to_string();
+
+        if self.cache_ttl > 0 {
+            if let Some(data) = self.load_cache(&cwd_key) {
+                return Some(data);
+            }
+        }
+
+        let output = self.run_command(&cwd, input)?;
+        let data = self.parse_output(&output)?;
+
+        if self.cache_ttl > 0 {
+            self.save_cache(&cwd_key, &data);
+        }
+
+        Some(data)
+    }
+
+    fn id(&self) -> SegmentId {
+        SegmentId::Custom
+    }
+}
+
+/// Kill the command's process group (unix) or the shell itself (elsewhere)
+fn kill_process_tree(child: &mut std::process::Child) {
+    #[cfg(unix)]
+    {
+        let group = format!("-{}", child.id());
+        let killed = Command::new("kill")
+            .args(["-KILL", "--", &group])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if killed {
+            return;
+        }
+    }
+    let _ = child.kill();
+}
+
+fn json_scalar_to_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
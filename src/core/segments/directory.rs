@@ -1,13 +1,134 @@
 use super::{Segment, SegmentData};
+use crate::config::schema::{OptionDefault, OptionKind, OptionSpec};
 use crate::config::{InputData, SegmentId};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Options read by `DirectorySegment::from_options`
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "mode",
+        kind: OptionKind::Enum(&["name", "full", "fish", "repo"]),
+        default: OptionDefault::Str("name"),
+        description: "Last component, full path, fish-style abbreviated or from the repo root",
+    },
+    OptionSpec {
+        name: "max_length",
+        kind: OptionKind::Integer { min: 0, max: 1000 },
+        default: OptionDefault::Integer(0),
+        description: "Maximum width, truncated from the left; 0 for no limit",
+    },
+    OptionSpec {
+        name: "aliases",
+        kind: OptionKind::StringMap(None),
+        default: OptionDefault::None,
+        description: "Path prefix → name shown instead, e.g. \"~/work/api\" = \"api\"",
+    },
+];
+
+/// How the path is shown, selected with the `mode` option
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DisplayMode {
+    /// Last path component only
+    #[default]
+    Name,
+    /// Full path with `~` for the home directory
+    Full,
+    /// Full path with every component but the last abbreviated (`~/w/p/crate`)
+    Fish,
+    /// Path from the git repository root (`repo/src/core`), full path outside
+    /// a repository
+    Repo,
+}
+
+impl DisplayMode {
+    fn from_option(value: Option<&str>) -> Self {
+        match value {
+            Some("full") => DisplayMode::Full,
+            Some("fish") => DisplayMode::Fish,
+            Some("repo") => DisplayMode::Repo,
+            _ => DisplayMode::Name,
+        }
+    }
+}
+
+/// A path split into components, remembering how it was written
+struct SplitPath {
+    components: Vec<String>,
+    /// Starts with a separator (Unix absolute path)
+    rooted: bool,
+    separator: char,
+}
+
+impl SplitPath {
+    /// Split on both Unix and Windows separators
+    fn parse(path: &str) -> Self {
+        let separator = if path.contains('\\') && !path.contains('/') {
+            '\\'
+        } else {
+            '/'
+        };
+        Self {
+            components: path
+                .split(['/', '\\'])
+                .filter(|c| !c.is_empty())
+                .map(str::to_string)
+                .collect(),
+            rooted: path.starts_with(['/', '\\']),
+            separator,
+        }
+    }
+
+    /// Number of leading components shared with `prefix`, if `prefix` is a
+    /// whole-component prefix of this path
+    fn strip_prefix(&self, prefix: &[String]) -> Option<usize> {
+        (!prefix.is_empty() && self.components.starts_with(prefix)).then_some(prefix.len())
+    }
+}
 
 #[derive(Default)]
-pub struct DirectorySegment;
+pub struct DirectorySegment {
+    mode: DisplayMode,
+    /// Maximum display width, truncated from the left with `…`
+    max_length: Option<usize>,
+    /// Path prefix (as components) → name shown instead
+    aliases: Vec<(Vec<String>, String)>,
+}
 
 impl DirectorySegment {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn from_options(options: &HashMap<String, serde_json::Value>) -> Self {
+        let mut segment = Self::new();
+        segment.mode = DisplayMode::from_option(options.get("mode").and_then(|v| v.as_str()));
+        segment.max_length = options
+            .get("max_length")
+            .and_then(|v| v.as_u64())
+            .filter(|&len| len > 0)
+            .map(|len| len as usize);
+
+        if let Some(aliases) = options.get("aliases").and_then(|v| v.as_object()) {
+            let home = Self::home_components();
+            for (path, alias) in aliases {
+                let Some(alias) = alias.as_str() else {
+                    continue;
+                };
+                let mut components = SplitPath::parse(path).components;
+                if components.first().map(String::as_str) == Some("~") {
+                    components.splice(..1, home.iter().cloned());
+                }
+                segment.aliases.push((components, alias.to_string()));
+            }
+            // Longest prefix wins
+            segment
+                .aliases
+                .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        }
+
+        segment
     }
 
     /// Extract directory name from path, handling both Unix and Windows separators
@@ -34,6 +155,116 @@ impl DirectorySegment {
             result.to_string()
         }
     }
+
+    fn home_components() -> Vec<String> {
+        dirs::home_dir()
+            .map(|home| SplitPath::parse(&home.to_string_lossy()).components)
+            .unwrap_or_default()
+    }
+
+    /// Nearest ancestor (or the directory itself) containing a `.git` entry,
+    /// as a number of leading components
+    fn repo_root_len(path: &str, split: &SplitPath) -> Option<usize> {
+        let mut dir = PathBuf::from(path);
+        let mut len = split.components.len();
+        loop {
+            if dir.join(".git").exists() {
+                return Some(len);
+            }
+            if !dir.pop() || len == 0 {
+                return None;
+            }
+            len -= 1;
+        }
+    }
+
+    /// Render the path for the configured mode
+    fn display_path(&self, path: &str) -> String {
+        let split = SplitPath::parse(path);
+        let alias_for = |len: usize| {
+            self.aliases
+                .iter()
+                .find(|(prefix, _)| prefix.len() == len && split.strip_prefix(prefix).is_some())
+                .map(|(_, alias)| alias.clone())
+        };
+
+        if self.mode == DisplayMode::Name {
+            return alias_for(split.components.len())
+                .unwrap_or_else(|| Self::extract_directory_name(path));
+        }
+
+        // The leading part of the path shown as a single label
+        let mut anchor: Option<(String, usize)> = None;
+        if self.mode == DisplayMode::Repo {
+            anchor = Self::repo_root_len(path, &split)
+                .filter(|&len| len > 0)
+                .and_then(|len| {
+                    let name = alias_for(len).or_else(|| split.components.get(len - 1).cloned())?;
+                    Some((name, len))
+                });
+        }
+        if anchor.is_none() {
+            anchor = self
+                .aliases
+                .iter()
+                .find_map(|(prefix, alias)| Some((alias.clone(), split.strip_prefix(prefix)?)));
+        }
+        if anchor.is_none() {
+            anchor = split
+                .strip_prefix(&Self::home_components())
+                .map(|len| ("~".to_string(), len));
+        }
+
+        let (mut parts, rest) = match anchor {
+            Some((label, len)) => (vec![label], &split.components[len..]),
+            None => (Vec::new(), &split.components[..]),
+        };
+        for (i, component) in rest.iter().enumerate() {
+            let is_last = i + 1 == rest.len();
+            if self.mode == DisplayMode::Fish && !is_last {
+                parts.push(Self::abbreviate(component));
+            } else {
+                parts.push(component.clone());
+            }
+        }
+
+        let joined = parts.join(&split.separator.to_string());
+        if split.rooted && parts.len() == rest.len() {
+            format!("{}{}", split.separator, joined)
+        } else {
+            joined
+        }
+    }
+
+    /// First character of a component, keeping a leading dot (`.config` → `.c`)
+    fn abbreviate(component: &str) -> String {
+        let mut chars = component.chars();
+        match chars.next() {
+            Some('.') => chars.next().map(|c| format!(".{}", c)).unwrap_or_default(),
+            Some(c) => c.to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Keep the end of `text` within `max_width` columns, marking the cut
+    /// with `…`
+    fn truncate_left(text: &str, max_width: usize) -> String {
+        if text.width() <= max_width {
+            return text.to_string();
+        }
+
+        let mut kept = Vec::new();
+        let mut width = 1; // the ellipsis
+        for c in text.chars().rev() {
+            let char_width = c.width().unwrap_or(0);
+            if width + char_width > max_width {
+                break;
+            }
+            width += char_width;
+            kept.push(c);
+        }
+        std::iter::once('…').chain(kept.into_iter().rev()).collect()
+    }
 }
 
 impl Segment for DirectorySegment {
@@ -43,12 +274,19 @@ impl Segment for DirectorySegment {
         // Handle cross-platform path separators manually for better compatibility
         let dir_name = Self::extract_directory_name(current_dir);
 
+        let mut display_path = self.display_path(current_dir);
+        if let Some(max_length) = self.max_length {
+            display_path = Self::truncate_left(&display_path, max_length);
+        }
+
         // Store the full path in metadata for potential use
         let mut metadata = HashMap::new();
         metadata.insert("full_path".to_string(), current_dir.clone());
+        metadata.insert("dir_name".to_string(), dir_name);
+        metadata.insert("display_path".to_string(), display_path.clone());
 
         Some(SegmentData {
-            primary: dir_name,
+            primary: display_path,
             secondary: String::new(),
             metadata,
         })
@@ -1,26 +1,530 @@
 use super::{Segment, SegmentData};
+use crate::config::schema::{OptionDefault, OptionKind, OptionSpec};
 use crate::config::{InputData, SegmentId};
+use crate::core::template;
 use std::collections::HashMap;
-use std::process::Command;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Default text templates (see `core::template`), overridable via the
+/// `format` / `secondary_format` options. The repository is named only
+/// when it differs from what the directory segment suggests.
+const DEFAULT_FORMAT: &str =
+    "{?superproject:{superproject}/{repo} }{?worktree:{repo}[{worktree}] }{branch}";
+const DEFAULT_SECONDARY_FORMAT: &str = concat!(
+    "{status_icon}{?operation: {operation_glyph}}{?changes: {changes}}",
+    "{?ahead: ↑{ahead}}{?behind: ↓{behind}}{?sha: {sha}}{?diff: {diff}}"
+);
+
+/// Time budget for computing the work tree status, overridable via the
+/// `status_timeout_ms` option. Past it the status is reported as unknown.
+const DEFAULT_STATUS_TIMEOUT_MS: u64 = 500;
+
+/// Time budget for line statistics (`diff_timeout_ms` option)
+const DEFAULT_DIFF_TIMEOUT_MS: u64 = 300;
+
+/// Changed files above which line statistics are skipped (`diff_max_files`)
+const DEFAULT_DIFF_MAX_FILES: u64 = 1000;
+
+/// Options read when building the git segment
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "show_sha",
+        kind: OptionKind::Bool,
+        default: OptionDefault::Bool(false),
+        description: "Show the short commit SHA",
+    },
+    OptionSpec {
+        name: "backend",
+        kind: OptionKind::Enum(&["auto", "native", "subprocess"]),
+        default: OptionDefault::Str("auto"),
+        description: "libgit2 with git fallback (auto), libgit2 only, or the git command",
+    },
+    OptionSpec {
+        name: "status_timeout_ms",
+        kind: OptionKind::Integer {
+            min: 1,
+            max: 60_000,
+        },
+        default: OptionDefault::Integer(DEFAULT_STATUS_TIMEOUT_MS),
+        description: "Time budget for the work tree status",
+    },
+    OptionSpec {
+        name: "diff_stats",
+        kind: OptionKind::Bool,
+        default: OptionDefault::Bool(false),
+        description: "Show added/removed line counts",
+    },
+    OptionSpec {
+        name: "diff_staged",
+        kind: OptionKind::Bool,
+        default: OptionDefault::Bool(false),
+        description: "Count staged lines separately",
+    },
+    OptionSpec {
+        name: "diff_timeout_ms",
+        kind: OptionKind::Integer {
+            min: 1,
+            max: 60_000,
+        },
+        default: OptionDefault::Integer(DEFAULT_DIFF_TIMEOUT_MS),
+        description: "Time budget for line counts",
+    },
+    OptionSpec {
+        name: "diff_max_files",
+        kind: OptionKind::Integer {
+            min: 0,
+            max: 1_000_000,
+        },
+        default: OptionDefault::Integer(DEFAULT_DIFF_MAX_FILES),
+        description: "Skip line counts above this many changed files",
+    },
+    OptionSpec {
+        name: "glyphs",
+        kind: OptionKind::StringMap(Some(&[
+            "staged",
+            "modified",
+            "deleted",
+            "renamed",
+            "untracked",
+            "conflicted",
+            "stash",
+            "rebase",
+            "merge",
+            "cherry_pick",
+            "revert",
+            "bisect",
+        ])),
+        default: OptionDefault::None,
+        description: "Replacement status glyphs, e.g. modified = \"*\"",
+    },
+];
+
+/// Object id of the empty tree, to diff against before the first commit
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// How repository information is read, selected with the `backend` option
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitBackend {
+    /// In-process via libgit2, falling back to the git binary for
+    /// repositories libgit2 can't open
+    Auto,
+    /// In-process via libgit2 only
+    Native,
+    /// Spawn the git binary
+    Subprocess,
+}
+
+impl GitBackend {
+    pub fn from_option(value: Option<&str>) -> Self {
+        match value {
+            Some("native") => GitBackend::Native,
+            Some("subprocess") => GitBackend::Subprocess,
+            _ => GitBackend::Auto,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct GitInfo {
+    /// Branch name, or the nearest tag / short SHA on a detached HEAD
     pub branch: String,
-    pub status: GitStatus,
+    pub detached: bool,
+    pub location: GitLocation,
+    /// File counts, `None` if the status failed or didn't finish within the
+    /// time budget
+    pub changes: Option<GitChanges>,
+    pub stash: u32,
+    pub operation: Option<GitOperation>,
+    /// Line statistics, if enabled with the `diff_stats` option
+    pub diff: Option<DiffStats>,
     pub ahead: u32,
     pub behind: u32,
     pub sha: Option<String>,
 }
 
+impl GitInfo {
+    pub fn status(&self) -> GitStatus {
+        match &self.changes {
+            None => GitStatus::Unknown,
+            Some(changes) if changes.conflicted > 0 => GitStatus::Conflicts,
+            Some(changes) if changes.is_clean() => GitStatus::Clean,
+            Some(_) => GitStatus::Dirty,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum GitStatus {
     Clean,
     Dirty,
     Conflicts,
+    /// Status could not be computed within the time budget
+    Unknown,
+}
+
+/// Number of files in each state. A file can count in several states, e.g.
+/// staged and modified again in the work tree.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GitChanges {
+    /// Changes in the index
+    pub staged: u32,
+    /// Unstaged modifications in the work tree
+    pub modified: u32,
+    /// Unstaged deletions
+    pub deleted: u32,
+    /// Staged renames
+    pub renamed: u32,
+    pub untracked: u32,
+    /// Unmerged paths
+    pub conflicted: u32,
+}
+
+impl GitChanges {
+    pub fn is_clean(&self) -> bool {
+        *self == GitChanges::default()
+    }
+
+    /// Number of tracked files with changes, a rough measure of diff size
+    fn changed_files(&self) -> u32 {
+        self.staged + self.modified + self.deleted + self.conflicted
+    }
+
+    /// Count the entries of `git status --porcelain=v2`
+    fn from_porcelain_v2(output: &str) -> Self {
+        let mut changes = GitChanges::default();
+        for line in output.lines() {
+            let mut fields = line.split(' ');
+            match fields.next() {
+                Some(kind @ ("1" | "2")) => {
+                    let xy = fields.next().unwrap_or("..").as_bytes();
+                    let (x, y) = (xy.first().copied(), xy.get(1).copied());
+                    if x != Some(b'.') {
+                        changes.staged += 1;
+                    }
+                    if kind == "2" {
+                        changes.renamed += 1;
+                    }
+                    match y {
+                        Some(b'M' | b'T') => changes.modified += 1,
+                        Some(b'D') => changes.deleted += 1,
+                        _ => {}
+                    }
+                }
+                Some("u") => changes.conflicted += 1,
+                Some("?") => changes.untracked += 1,
+                _ => {}
+            }
+        }
+        changes
+    }
+}
+
+/// Operation left in progress in the repository
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitOperation {
+    Rebase,
+    Merge,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+
+impl GitOperation {
+    /// Detect the operation from the state files git leaves in the
+    /// (per-worktree) git directory
+    pub fn detect(git_dir: &Path) -> Option<Self> {
+        let markers = [
+            ("rebase-merge", GitOperation::Rebase),
+            ("rebase-apply", GitOperation::Rebase),
+            ("MERGE_HEAD", GitOperation::Merge),
+            ("CHERRY_PICK_HEAD", GitOperation::CherryPick),
+            ("REVERT_HEAD", GitOperation::Revert),
+            ("BISECT_LOG", GitOperation::Bisect),
+        ];
+        markers
+            .iter()
+            .find(|(marker, _)| git_dir.join(marker).exists())
+            .map(|(_, operation)| *operation)
+    }
+
+    /// Metadata value and glyph key
+    pub fn key(self) -> &'static str {
+        match self {
+            GitOperation::Rebase => "rebase",
+            GitOperation::Merge => "merge",
+            GitOperation::CherryPick => "cherry_pick",
+            GitOperation::Revert => "revert",
+            GitOperation::Bisect => "bisect",
+        }
+    }
+}
+
+/// Lines added and removed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LineDelta {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl LineDelta {
+    /// Sum the output of `git diff --numstat` (binary files count as zero)
+    fn from_numstat(output: &str) -> Self {
+        let mut delta = LineDelta::default();
+        for line in output.lines() {
+            let mut fields = line.split('\t');
+            let added = fields.next().and_then(|n| n.parse::<usize>().ok());
+            let removed = fields.next().and_then(|n| n.parse::<usize>().ok());
+            delta.added += added.unwrap_or(0);
+            delta.removed += removed.unwrap_or(0);
+        }
+        delta
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffStats {
+    Counted {
+        /// Work tree (staged and unstaged changes) against HEAD
+        worktree: LineDelta,
+        /// Index against HEAD, if `diff_staged` is enabled
+        staged: Option<LineDelta>,
+    },
+    /// Too many changed files, or the diff failed or exceeded its time budget
+    Skipped,
+}
+
+/// Line statistics settings, from the `diff_*` options
+#[derive(Debug, Clone)]
+pub struct DiffSettings {
+    pub staged: bool,
+    pub timeout: Duration,
+    pub max_files: u32,
+}
+
+impl DiffSettings {
+    /// `None` unless the `diff_stats` option is enabled
+    pub fn from_options(options: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let enabled = options
+            .get("diff_stats")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let get_u64 =
+            |key: &str, default: u64| options.get(key).and_then(|v| v.as_u64()).unwrap_or(default);
+        Some(Self {
+            staged: options
+                .get("diff_staged")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            timeout: Duration::from_millis(get_u64("diff_timeout_ms", DEFAULT_DIFF_TIMEOUT_MS)),
+            max_files: get_u64("diff_max_files", DEFAULT_DIFF_MAX_FILES) as u32,
+        })
+    }
+
+    /// Result known from the status alone, without running a diff: zero
+    /// for a clean tree, skipped for a large or timed-out one
+    pub fn precheck(&self, changes: Option<&GitChanges>) -> Option<DiffStats> {
+        match changes {
+            None => Some(DiffStats::Skipped),
+            Some(changes) if changes.changed_files() > self.max_files => Some(DiffStats::Skipped),
+            Some(changes) if changes.changed_files() == 0 => Some(DiffStats::Counted {
+                worktree: LineDelta::default(),
+                staged: self.staged.then(LineDelta::default),
+            }),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Which repository and worktree the working directory belongs to
+#[derive(Debug, Default)]
+pub struct GitLocation {
+    /// Name of the repository (of its main worktree for linked worktrees)
+    pub repo: String,
+    /// Directory name of the linked worktree, if not in the main one
+    pub worktree: Option<String>,
+    /// Name of the parent repository when inside a submodule
+    pub superproject: Option<String>,
+}
+
+impl GitLocation {
+    /// `workdir` is the top of the current worktree; `common_dir` the git
+    /// directory shared by all worktrees
+    pub fn new(workdir: &Path, common_dir: &Path, linked_worktree: bool) -> Self {
+        if linked_worktree {
+            Self {
+                repo: repo_name(common_dir),
+                worktree: Some(dir_name(workdir)),
+                superproject: None,
+            }
+        } else {
+            Self {
+                repo: dir_name(workdir),
+                worktree: None,
+                superproject: None,
+            }
+        }
+    }
+}
+
+/// A tag plus the number of commits HEAD is past it: `v1.3.0`, `v1.3.0+4`
+pub fn format_tag_distance(name: &str, distance: usize) -> String {
+    if distance == 0 {
+        name.to_string()
+    } else {
+        format!("{}+{}", name, distance)
+    }
+}
+
+pub fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned())
+}
+
+/// Name of a repository from its common git directory: the main worktree
+/// for `<main>/.git`, or the directory without `.git` for a bare repository
+fn repo_name(common_dir: &Path) -> String {
+    let name = dir_name(common_dir);
+    if name == ".git" {
+        common_dir.parent().map(dir_name).unwrap_or(name)
+    } else {
+        name.strip_suffix(".git").unwrap_or(&name).to_string()
+    }
+}
+
+/// Number of stash entries, one per line of the stash reflog in the common
+/// git directory
+pub fn stash_count(common_dir: &Path) -> u32 {
+    fs::read_to_string(common_dir.join("logs").join("refs").join("stash"))
+        .map(|log| log.lines().filter(|line| !line.is_empty()).count() as u32)
+        .unwrap_or(0)
+}
+
+/// Glyphs for file states and in-progress operations, overridable per key
+/// via the `glyphs` option table
+#[derive(Debug, Clone)]
+pub struct GitGlyphs {
+    glyphs: HashMap<String, String>,
+}
+
+impl Default for GitGlyphs {
+    fn default() -> Self {
+        let defaults = [
+            ("staged", "+"),
+            ("modified", "!"),
+            ("deleted", "✘"),
+            ("renamed", "»"),
+            ("untracked", "?"),
+            ("conflicted", "="),
+            ("stash", "$"),
+            ("rebase", "REBASING"),
+            ("merge", "MERGING"),
+            ("cherry_pick", "CHERRY-PICKING"),
+            ("revert", "REVERTING"),
+            ("bisect", "BISECTING"),
+        ];
+        Self {
+            glyphs: defaults
+                .iter()
+                .map(|(key, glyph)| (key.to_string(), glyph.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl GitGlyphs {
+    pub fn from_options(options: &HashMap<String, serde_json::Value>) -> Self {
+        let mut glyphs = Self::default();
+        if let Some(table) = options.get("glyphs").and_then(|v| v.as_object()) {
+            for (key, value) in table {
+                if let (true, Some(glyph)) = (glyphs.glyphs.contains_key(key), value.as_str()) {
+                    glyphs.glyphs.insert(key.clone(), glyph.to_string());
+                }
+            }
+        }
+        glyphs
+    }
+
+    pub fn get(&self, key: &str) -> &str {
+        self.glyphs.get(key).map(String::as_str).unwrap_or("")
+    }
+}
+
+/// Outcome of a git command run with a deadline
+enum TimedRun {
+    Output(String),
+    Failed,
+    TimedOut,
+}
+
+/// Run git, killing it if it is still running at `deadline`
+fn run_git_timed(working_dir: &str, args: &[&str], deadline: Instant) -> TimedRun {
+    let Ok(mut child) = Command::new("git")
+        .arg("--no-optional-locks")
+        .args(args)
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return TimedRun::Failed;
+    };
+
+    // Drain stdout on a separate thread so large output can't block on a
+    // full pipe while we wait for it
+    let Some(mut stdout) = child.stdout.take() else {
+        return TimedRun::Failed;
+    };
+    let reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout.read_to_string(&mut buf);
+        buf
+    });
+
+    let exit_status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return TimedRun::TimedOut;
+            }
+            Err(_) => return TimedRun::Failed,
+        }
+    };
+
+    match reader.join() {
+        Ok(output) if exit_status.success() => TimedRun::Output(output),
+        _ => TimedRun::Failed,
+    }
+}
+
+/// Output of `git rev-parse` for the subprocess backend
+struct GitDirs {
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    toplevel: PathBuf,
+    superproject: Option<PathBuf>,
 }
 
 pub struct GitSegment {
     show_sha: bool,
+    backend: GitBackend,
+    status_timeout: Duration,
+    glyphs: GitGlyphs,
+    diff: Option<DiffSettings>,
 }
 
 impl Default for GitSegment {
@@ -31,7 +535,13 @@ impl Default for GitSegment {
 
 impl GitSegment {
     pub fn new() -> Self {
-        Self { show_sha: false }
+        Self {
+            show_sha: false,
+            backend: GitBackend::Auto,
+            status_timeout: Duration::from_millis(DEFAULT_STATUS_TIMEOUT_MS),
+            glyphs: GitGlyphs::default(),
+            diff: None,
+        }
     }
 
     pub fn with_sha(mut self, show_sha: bool) -> Self {
@@ -39,15 +549,64 @@ impl GitSegment {
         self
     }
 
+    pub fn with_backend(mut self, backend: GitBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_status_timeout(mut self, timeout: Duration) -> Self {
+        self.status_timeout = timeout;
+        self
+    }
+
+    pub fn with_glyphs(mut self, glyphs: GitGlyphs) -> Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+    pub fn with_diff(mut self, diff: Option<DiffSettings>) -> Self {
+        self.diff = diff;
+        self
+    }
+
     fn get_git_info(&self, working_dir: &str) -> Option<GitInfo> {
-        if !self.is_git_repository(working_dir) {
-            return None;
+        #[cfg(feature = "native-git")]
+        if self.backend != GitBackend::Subprocess {
+            match super::git_native::read_git_info(
+                working_dir,
+                self.show_sha,
+                self.status_timeout,
+                self.diff.as_ref(),
+            ) {
+                Ok(info) => return info,
+                Err(_) if self.backend == GitBackend::Native => return None,
+                // Unsupported layout or extension: let the git binary try
+                Err(_) => {}
+            }
         }
 
-        let branch = self
-            .get_branch(working_dir)
-            .unwrap_or_else(|| "detached".to_string());
-        let status = self.get_status(working_dir);
+        self.get_git_info_subprocess(working_dir)
+    }
+
+    fn get_git_info_subprocess(&self, working_dir: &str) -> Option<GitInfo> {
+        let dirs = self.get_git_dirs(working_dir)?;
+
+        let (branch, detached) = match self.get_branch(working_dir) {
+            Some(branch) => (branch, false),
+            None => (self.describe_detached(working_dir), true),
+        };
+        let linked_worktree = match (dirs.git_dir.canonicalize(), dirs.common_dir.canonicalize()) {
+            (Ok(git_dir), Ok(common_dir)) => git_dir != common_dir,
+            _ => false,
+        };
+        let mut location = GitLocation::new(&dirs.toplevel, &dirs.common_dir, linked_worktree);
+        location.superproject = dirs.superproject.as_deref().map(dir_name);
+        let changes = self.get_changes(working_dir);
+        let diff = self.diff.as_ref().map(|settings| {
+            settings
+                .precheck(changes.as_ref())
+                .unwrap_or_else(|| self.get_diff_stats(working_dir, settings))
+        });
         let (ahead, behind) = self.get_ahead_behind(working_dir);
         let sha = if self.show_sha {
             self.get_sha(working_dir)
@@ -57,20 +616,79 @@ impl GitSegment {
 
         Some(GitInfo {
             branch,
-            status,
+            detached,
+            location,
+            changes,
+            stash: stash_count(&dirs.common_dir),
+            operation: GitOperation::detect(&dirs.git_dir),
+            diff,
             ahead,
             behind,
             sha,
         })
     }
 
-    fn is_git_repository(&self, working_dir: &str) -> bool {
-        Command::new("git")
-            .args(["--no-optional-locks", "rev-parse", "--git-dir"])
+    /// Locate the repository, or `None` outside a work tree
+    fn get_git_dirs(&self, working_dir: &str) -> Option<GitDirs> {
+        let output = Command::new("git")
+            .args([
+                "--no-optional-locks",
+                "rev-parse",
+                "--git-dir",
+                "--git-common-dir",
+                "--show-toplevel",
+                "--show-superproject-working-tree",
+            ])
             .current_dir(working_dir)
             .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        // Paths may be relative to the working directory
+        let stdout = String::from_utf8(output.stdout).ok()?;
+        let mut dirs = stdout.lines().map(|dir| Path::new(working_dir).join(dir));
+        Some(GitDirs {
+            git_dir: dirs.next()?,
+            common_dir: dirs.next()?,
+            toplevel: dirs.next()?,
+            // Printed only inside a submodule
+            superproject: dirs.next(),
+        })
+    }
+
+    /// Name a detached HEAD after the nearest tag behind it on the
+    /// first-parent chain (e.g. `v1.3.0+4`), or its short SHA if there is none
+    fn describe_detached(&self, working_dir: &str) -> String {
+        let output = Command::new("git")
+            .args([
+                "--no-optional-locks",
+                "describe",
+                "--tags",
+                "--first-parent",
+                "--long",
+                "HEAD",
+            ])
+            .current_dir(working_dir)
+            .output();
+
+        // `<tag>-<distance>-g<sha>`; the tag itself may contain dashes
+        let tag = match output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8(output.stdout).ok().and_then(|described| {
+                    let mut parts = described.trim().rsplitn(3, '-');
+                    let _sha = parts.next()?;
+                    let distance = parts.next()?.parse().ok()?;
+                    let name = parts.next().filter(|name| !name.is_empty())?;
+                    Some(format_tag_distance(name, distance))
+                })
+            }
+            _ => None,
+        };
+
+        tag.or_else(|| self.get_sha(working_dir))
+            .unwrap_or_else(|| "detached".to_string())
     }
 
     fn get_branch(&self, working_dir: &str) -> Option<String> {
@@ -103,31 +721,44 @@ impl GitSegment {
         None
     }
 
-    fn get_status(&self, working_dir: &str) -> GitStatus {
-        let output = Command::new("git")
-            .args(["--no-optional-locks", "status", "--porcelain"])
-            .current_dir(working_dir)
-            .output();
+    /// Run `git status --porcelain=v2` within the time budget. Returns
+    /// `None` if it failed or had to be killed, so the status shows as
+    /// unknown rather than clean.
+    fn get_changes(&self, working_dir: &str) -> Option<GitChanges> {
+        let deadline = Instant::now() + self.status_timeout;
+        match run_git_timed(working_dir, &["status", "--porcelain=v2"], deadline) {
+            TimedRun::Output(status_text) => Some(GitChanges::from_porcelain_v2(&status_text)),
+            TimedRun::Failed | TimedRun::TimedOut => None,
+        }
+    }
 
-        match output {
-            Ok(output) if output.status.success() => {
-                let status_text = String::from_utf8(output.stdout).unwrap_or_default();
+    /// Run `git diff --numstat` against HEAD (the empty tree before the
+    /// first commit) for the work tree and optionally the index
+    fn get_diff_stats(&self, working_dir: &str, settings: &DiffSettings) -> DiffStats {
+        let deadline = Instant::now() + settings.timeout;
+        let base = if self.get_sha(working_dir).is_some() {
+            "HEAD"
+        } else {
+            EMPTY_TREE
+        };
 
-                if status_text.trim().is_empty() {
-                    return GitStatus::Clean;
-                }
+        let numstat = |args: &[&str]| match run_git_timed(working_dir, args, deadline) {
+            TimedRun::Output(output) => Some(LineDelta::from_numstat(&output)),
+            TimedRun::Failed | TimedRun::TimedOut => None,
+        };
 
-                if status_text.contains("UU")
-                    || status_text.contains("AA")
-                    || status_text.contains("DD")
-                {
-                    GitStatus::Conflicts
-                } else {
-                    GitStatus::Dirty
-                }
+        let Some(worktree) = numstat(&["diff", "--numstat", base]) else {
+            return DiffStats::Skipped;
+        };
+        let staged = if settings.staged {
+            match numstat(&["diff", "--numstat", "--cached", base]) {
+                Some(staged) => Some(staged),
+                None => return DiffStats::Skipped,
             }
-            _ => GitStatus::Clean,
-        }
+        } else {
+            None
+        };
+        DiffStats::Counted { worktree, staged }
     }
 
     fn get_ahead_behind(&self, working_dir: &str) -> (u32, u32) {
@@ -174,10 +805,19 @@ impl GitSegment {
 impl Segment for GitSegment {
     fn collect(&self, input: &InputData) -> Option<SegmentData> {
         let git_info = self.get_git_info(&input.workspace.current_dir)?;
+        let status = git_info.status();
 
         let mut metadata = HashMap::new();
         metadata.insert("branch".to_string(), git_info.branch.clone());
-        metadata.insert("status".to_string(), format!("{:?}", git_info.status));
+        metadata.insert("detached".to_string(), git_info.detached.to_string());
+        metadata.insert("repo".to_string(), git_info.location.repo.clone());
+        if let Some(worktree) = &git_info.location.worktree {
+            metadata.insert("worktree".to_string(), worktree.clone());
+        }
+        if let Some(superproject) = &git_info.location.superproject {
+            metadata.insert("superproject".to_string(), superproject.clone());
+        }
+        metadata.insert("status".to_string(), format!("{:?}", status));
         metadata.insert("ahead".to_string(), git_info.ahead.to_string());
         metadata.insert("behind".to_string(), git_info.behind.to_string());
 
@@ -185,29 +825,72 @@ impl Segment for GitSegment {
             metadata.insert("sha".to_string(), sha.clone());
         }
 
-        let primary = git_info.branch;
-        let mut status_parts = Vec::new();
+        let status_icon = match status {
+            GitStatus::Clean => "✓",
+            GitStatus::Dirty => "●",
+            GitStatus::Conflicts => "⚠",
+            GitStatus::Unknown => "?",
+        };
+        metadata.insert("status_icon".to_string(), status_icon.to_string());
 
-        match git_info.status {
-            GitStatus::Clean => status_parts.push("✓".to_string()),
-            GitStatus::Dirty => status_parts.push("●".to_string()),
-            GitStatus::Conflicts => status_parts.push("⚠".to_string()),
+        // Per-state counts, plus a compact summary like "+3 !2 ?5"
+        let mut summary = Vec::new();
+        if let Some(changes) = &git_info.changes {
+            let counts = [
+                ("conflicted", changes.conflicted),
+                ("staged", changes.staged),
+                ("renamed", changes.renamed),
+                ("modified", changes.modified),
+                ("deleted", changes.deleted),
+                ("untracked", changes.untracked),
+            ];
+            for (key, count) in counts {
+                metadata.insert(key.to_string(), count.to_string());
+                if count > 0 {
+                    summary.push(format!("{}{}", self.glyphs.get(key), count));
+                }
+            }
         }
-
-        if git_info.ahead > 0 {
-            status_parts.push(format!("↑{}", git_info.ahead));
+        metadata.insert("stash".to_string(), git_info.stash.to_string());
+        if git_info.stash > 0 {
+            summary.push(format!("{}{}", self.glyphs.get("stash"), git_info.stash));
         }
-        if git_info.behind > 0 {
-            status_parts.push(format!("↓{}", git_info.behind));
+        if !summary.is_empty() {
+            metadata.insert("changes".to_string(), summary.join(" "));
         }
 
-        if let Some(ref sha) = git_info.sha {
-            status_parts.push(sha.clone());
+        match &git_info.diff {
+            Some(DiffStats::Counted { worktree, staged }) => {
+                metadata.insert("lines_added".to_string(), worktree.added.to_string());
+                metadata.insert("lines_removed".to_string(), worktree.removed.to_string());
+                if worktree.added + worktree.removed > 0 {
+                    metadata.insert(
+                        "diff".to_string(),
+                        format!("+{} \u{2212}{}", worktree.added, worktree.removed),
+                    );
+                }
+                if let Some(staged) = staged {
+                    metadata.insert("staged_added".to_string(), staged.added.to_string());
+                    metadata.insert("staged_removed".to_string(), staged.removed.to_string());
+                }
+            }
+            Some(DiffStats::Skipped) => {
+                metadata.insert("diff_skipped".to_string(), "true".to_string());
+            }
+            None => {}
+        }
+
+        if let Some(operation) = git_info.operation {
+            metadata.insert("operation".to_string(), operation.key().to_string());
+            metadata.insert(
+                "operation_glyph".to_string(),
+                self.glyphs.get(operation.key()).to_string(),
+            );
         }
 
         Some(SegmentData {
-            primary,
-            secondary: status_parts.join(" "),
+            primary: template::render(DEFAULT_FORMAT, &metadata),
+            secondary: template::render(DEFAULT_SECONDARY_FORMAT, &metadata),
             metadata,
         })
     }
@@ -216,3 +899,53 @@ impl Segment for GitSegment {
         SegmentId::Git
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_v2_records_are_counted() {
+        let output = [
+            "# branch.oid 4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+            "# branch.head main",
+            "# branch.upstream origin/main",
+            "# branch.ab +1 -2",
+            "# stash 3",
+            "1 M. N... 100644 100644 100644 1111111 2222222 src/staged.rs",
+            "1 .M N... 100644 100644 100644 1111111 1111111 src/modified.rs",
+            "1 MM N... 100644 100644 100644 1111111 2222222 src/both.rs",
+            "1 .D N... 100644 100644 000000 1111111 1111111 src/deleted.rs",
+            "1 D. N... 100644 000000 000000 1111111 0000000 src/removed.rs",
+            "1 .T N... 100644 100644 120000 1111111 1111111 src/link",
+            "1 A. N... 000000 100644 100644 0000000 2222222 src/with space.rs",
+            "2 R. N... 100644 100644 100644 1111111 1111111 R100 src/new.rs\tsrc/old.rs",
+            "2 RM N... 100644 100644 100644 1111111 2222222 R87 src/b.rs\tsrc/a.rs",
+            "u UU N... 100644 100644 100644 100644 1111111 2222222 3333333 src/conflict.rs",
+            "? notes.txt",
+            "? build/out.log",
+            "! target/",
+        ]
+        .join("\n");
+
+        assert_eq!(
+            GitChanges::from_porcelain_v2(&output),
+            GitChanges {
+                staged: 6,
+                modified: 4,
+                deleted: 1,
+                renamed: 2,
+                untracked: 2,
+                conflicted: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn porcelain_v2_headers_alone_are_clean() {
+        // `# stash` is only a header; the stash count comes from the reflog
+        let output = "# branch.oid 4b825dc\n# branch.head main\n# stash 5\n";
+        assert!(GitChanges::from_porcelain_v2(output).is_clean());
+        assert!(GitChanges::from_porcelain_v2("").is_clean());
+    }
+}
@@ -0,0 +1,267 @@
+//! In-process git backend for `GitSegment`, built on libgit2.
+
+use super::git::{
+    dir_name, format_tag_distance, stash_count, DiffSettings, DiffStats, GitChanges, GitInfo,
+    GitLocation, GitOperation, LineDelta,
+};
+use git2::{BranchType, ErrorCode, Oid, Repository, Status, StatusOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How far back from a detached HEAD to look for a tag
+const MAX_TAG_DISTANCE: usize = 1000;
+
+/// Read branch, file counts, stash, in-progress operation, ahead/behind and
+/// optionally the short SHA without spawning git. Returns `Ok(None)` outside
+/// a work tree and `Err` when libgit2 can't handle the repository, so the
+/// caller can fall back to the git binary.
+pub fn read_git_info(
+    working_dir: &str,
+    show_sha: bool,
+    status_timeout: Duration,
+    diff_settings: Option<&DiffSettings>,
+) -> Result<Option<GitInfo>, git2::Error> {
+    let repo = match Repository::discover(working_dir) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let Some(workdir) = repo.workdir().map(|dir| dir.to_path_buf()) else {
+        // Bare repository: nothing to show a status for
+        return Ok(None);
+    };
+
+    // Start the status scan first; it is the slow part on big repositories
+    let status_rx = spawn_status_scan(workdir.clone());
+    let status_deadline = Instant::now() + status_timeout;
+
+    let mut location = GitLocation::new(&workdir, repo.commondir(), repo.is_worktree());
+    location.superproject = superproject_name(&workdir);
+
+    let head = match repo.head() {
+        Ok(head) => Some(head),
+        Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e),
+    };
+
+    let branch = match &head {
+        Some(head) if head.is_branch() => head.shorthand().map(str::to_string),
+        // Detached HEAD
+        Some(_) => None,
+        // Unborn branch: HEAD still names the branch it will create
+        None => repo.find_reference("HEAD").ok().and_then(|reference| {
+            reference
+                .symbolic_target()
+                .map(|target| target.trim_start_matches("refs/heads/").to_string())
+        }),
+    };
+
+    let (ahead, behind) = match (&head, &branch) {
+        (Some(head), Some(name)) if head.is_branch() => head
+            .target()
+            .and_then(|local| {
+                let upstream = repo
+                    .find_branch(name, BranchType::Local)
+                    .ok()?
+                    .upstream()
+                    .ok()?
+                    .get()
+                    .target()?;
+                repo.graph_ahead_behind(local, upstream).ok()
+            })
+            .unwrap_or((0, 0)),
+        _ => (0, 0),
+    };
+
+    let short_sha = || {
+        head.as_ref()
+            .and_then(|head| head.target())
+            .and_then(|oid| repo.find_object(oid, None).ok())
+            .and_then(|object| object.short_id().ok())
+            .and_then(|short| short.as_str().map(str::to_string))
+    };
+    let sha = if show_sha { short_sha() } else { None };
+
+    let detached = head.as_ref().is_some_and(|head| !head.is_branch());
+    let branch = if detached {
+        head.as_ref()
+            .and_then(|head| head.target())
+            .and_then(|oid| nearest_tag(&repo, oid, status_deadline))
+            .or_else(short_sha)
+    } else {
+        branch
+    };
+
+    let status_wait = status_deadline.saturating_duration_since(Instant::now());
+    let changes = match status_rx.recv_timeout(status_wait) {
+        Ok(result) => Some(result?),
+        Err(_) => None,
+    };
+
+    let diff = diff_settings.map(|settings| {
+        settings.precheck(changes.as_ref()).unwrap_or_else(|| {
+            let diff_rx = spawn_diff_stats(workdir.clone(), settings.staged);
+            match diff_rx.recv_timeout(settings.timeout) {
+                Ok(Ok(stats)) => stats,
+                _ => DiffStats::Skipped,
+            }
+        })
+    });
+
+    Ok(Some(GitInfo {
+        branch: branch.unwrap_or_else(|| "detached".to_string()),
+        detached,
+        location,
+        changes,
+        stash: stash_count(repo.commondir()),
+        operation: GitOperation::detect(repo.path()),
+        diff,
+        ahead: ahead as u32,
+        behind: behind as u32,
+        sha,
+    }))
+}
+
+/// Name of the parent repository if `workdir` is one of its submodules
+fn superproject_name(workdir: &Path) -> Option<String> {
+    let parent = Repository::discover(workdir.parent()?).ok()?;
+    let parent_workdir = parent.workdir()?;
+    let relative = workdir.strip_prefix(parent_workdir).ok()?;
+    parent.find_submodule(relative.to_str()?).ok()?;
+    Some(dir_name(parent_workdir))
+}
+
+/// Nearest tag reachable from `head` along first parents, formatted like
+/// `git describe --tags` without the SHA (`v1.3.0`, `v1.3.0+4`). Gives up
+/// at `deadline`, leaving the caller to show the short SHA.
+fn nearest_tag(repo: &Repository, head: Oid, deadline: Instant) -> Option<String> {
+    // Tagged commit -> tag name, the first name in sort order if several
+    let mut tags: HashMap<Oid, String> = HashMap::new();
+    let _ = repo.tag_foreach(|oid, name| {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        let name = String::from_utf8_lossy(name);
+        let name = name.trim_start_matches("refs/tags/");
+        if let Ok(commit) = repo.find_object(oid, None).and_then(|o| o.peel_to_commit()) {
+            tags.entry(commit.id())
+                .and_modify(|existing| {
+                    if name < existing.as_str() {
+                        *existing = name.to_string();
+                    }
+                })
+                .or_insert_with(|| name.to_string());
+        }
+        true
+    });
+    if tags.is_empty() || Instant::now() >= deadline {
+        return None;
+    }
+
+    let mut commit = repo.find_commit(head).ok()?;
+    for distance in 0..=MAX_TAG_DISTANCE {
+        if let Some(name) = tags.get(&commit.id()) {
+            return Some(format_tag_distance(name, distance));
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        commit = commit.parent(0).ok()?;
+    }
+    None
+}
+
+/// Compute the work tree status on a separate thread. The caller stops
+/// waiting after its timeout; the scan is abandoned with the process.
+fn spawn_status_scan(workdir: PathBuf) -> mpsc::Receiver<Result<GitChanges, git2::Error>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = Repository::open(&workdir).and_then(|repo| read_status(&repo));
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+/// Compute line statistics against HEAD on a separate thread, like the
+/// status scan
+fn spawn_diff_stats(
+    workdir: PathBuf,
+    staged: bool,
+) -> mpsc::Receiver<Result<DiffStats, git2::Error>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = Repository::open(&workdir).and_then(|repo| read_diff_stats(&repo, staged));
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+/// Equivalent of `git diff --numstat HEAD` (and `--cached`), summed
+fn read_diff_stats(repo: &Repository, staged: bool) -> Result<DiffStats, git2::Error> {
+    // Before the first commit everything is diffed against the empty tree
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+
+    let delta = |diff: git2::Diff| -> Result<LineDelta, git2::Error> {
+        let stats = diff.stats()?;
+        Ok(LineDelta {
+            added: stats.insertions(),
+            removed: stats.deletions(),
+        })
+    };
+
+    let worktree = delta(repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), None)?)?;
+    let staged = if staged {
+        Some(delta(repo.diff_tree_to_index(
+            head_tree.as_ref(),
+            None,
+            None,
+        )?)?)
+    } else {
+        None
+    };
+    Ok(DiffStats::Counted { worktree, staged })
+}
+
+/// Equivalent of counting `git status --porcelain=v2` entries
+fn read_status(repo: &Repository) -> Result<GitChanges, git2::Error> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false)
+        .renames_head_to_index(true);
+
+    let staged_flags = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+
+    let mut changes = GitChanges::default();
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let status = entry.status();
+        if status.contains(Status::CONFLICTED) {
+            changes.conflicted += 1;
+            continue;
+        }
+        if status.intersects(staged_flags) {
+            changes.staged += 1;
+        }
+        if status.contains(Status::INDEX_RENAMED) {
+            changes.renamed += 1;
+        }
+        if status.intersects(Status::WT_MODIFIED | Status::WT_TYPECHANGE) {
+            changes.modified += 1;
+        }
+        if status.contains(Status::WT_DELETED) {
+            changes.deleted += 1;
+        }
+        if status.contains(Status::WT_NEW) {
+            changes.untracked += 1;
+        }
+    }
+    Ok(changes)
+}
@@ -1,7 +1,10 @@
 pub mod context_window;
 pub mod cost;
+pub mod custom;
 pub mod directory;
 pub mod git;
+#[cfg(feature = "native-git")]
+mod git_native;
 pub mod model;
 pub mod output_style;
 pub mod session;
@@ -10,6 +13,7 @@ pub mod update;
 pub mod usage;
 
 use crate::config::{InputData, SegmentId};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 // New Segment trait for data collection only
@@ -18,7 +22,7 @@ pub trait Segment {
     fn id(&self) -> SegmentId;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SegmentData {
     pub primary: String,
     pub secondary: String,
@@ -27,12 +31,13 @@ pub struct SegmentData {
 
 // Re-export all segment types
 pub use context_window::ContextWindowSegment;
-pub use cost::CostSegment;
+pub use cost::{CostEstimate, CostSegment};
+pub use custom::CustomSegment;
 pub use directory::DirectorySegment;
-pub use git::GitSegment;
+pub use git::{DiffSettings, GitBackend, GitGlyphs, GitSegment};
 pub use model::ModelSegment;
 pub use output_style::OutputStyleSegment;
 pub use session::SessionSegment;
 pub use subscription::SubscriptionSegment;
 pub use update::UpdateSegment;
-pub use usage::UsageSegment;
+pub use usage::{ResetFormat, UsageSegment, UsageWindow};
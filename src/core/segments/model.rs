@@ -13,12 +13,15 @@ impl ModelSegment {
 
 impl Segment for ModelSegment {
     fn collect(&self, input: &InputData) -> Option<SegmentData> {
+        let name = self.format_model_name(input, &input.model.id, &input.model.display_name);
+
         let mut metadata = HashMap::new();
         metadata.insert("model_id".to_string(), input.model.id.clone());
         metadata.insert("display_name".to_string(), input.model.display_name.clone());
+        metadata.insert("name".to_string(), name.clone());
 
         Some(SegmentData {
-            primary: self.format_model_name(&input.model.id, &input.model.display_name),
+            primary: name,
             secondary: String::new(),
             metadata,
         })
@@ -30,8 +33,8 @@ impl Segment for ModelSegment {
 }
 
 impl ModelSegment {
-    fn format_model_name(&self, id: &str, display_name: &str) -> String {
-        let model_config = ModelConfig::load();
+    fn format_model_name(&self, input: &InputData, id: &str, display_name: &str) -> String {
+        let model_config = ModelConfig::load_for_workspace(&input.workspace.current_dir);
 
         // Try to get display name from external config first
         if let Some(config_name) = model_config.get_display_name(id) {
@@ -1,7 +1,13 @@
 use super::{Segment, SegmentData};
 use crate::config::{InputData, SegmentId};
+use crate::core::template;
 use std::collections::HashMap;
 
+/// Default text templates (see `core::template`), overridable via the
+/// `format` / `secondary_format` options
+const DEFAULT_FORMAT: &str = "{duration}";
+const DEFAULT_SECONDARY_FORMAT: &str = "{line_changes}";
+
 #[derive(Default)]
 pub struct SessionSegment;
 
@@ -40,15 +46,10 @@ impl Segment for SessionSegment {
     fn collect(&self, input: &InputData) -> Option<SegmentData> {
         let cost_data = input.cost.as_ref()?;
 
-        // Primary display: total duration
-        let primary = if let Some(duration) = cost_data.total_duration_ms {
-            Self::format_duration(duration)
-        } else {
-            return None;
-        };
+        let duration = cost_data.total_duration_ms?;
 
-        // Secondary display: line changes if available
-        let secondary = match (cost_data.total_lines_added, cost_data.total_lines_removed) {
+        // Line changes if available
+        let line_changes = match (cost_data.total_lines_added, cost_data.total_lines_removed) {
             (Some(added), Some(removed)) if added > 0 || removed > 0 => {
                 format!("+{} -{}", added, removed)
             }
@@ -62,9 +63,9 @@ impl Segment for SessionSegment {
         };
 
         let mut metadata = HashMap::new();
-        if let Some(duration) = cost_data.total_duration_ms {
-            metadata.insert("duration_ms".to_string(), duration.to_string());
-        }
+        metadata.insert("duration_ms".to_string(), duration.to_string());
+        metadata.insert("duration".to_string(), Self::format_duration(duration));
+        metadata.insert("line_changes".to_string(), line_changes);
         if let Some(api_duration) = cost_data.total_api_duration_ms {
             metadata.insert("api_duration_ms".to_string(), api_duration.to_string());
         }
@@ -76,8 +77,8 @@ impl Segment for SessionSegment {
         }
 
         Some(SegmentData {
-            primary,
-            secondary,
+            primary: template::render(DEFAULT_FORMAT, &metadata),
+            secondary: template::render(DEFAULT_SECONDARY_FORMAT, &metadata),
             metadata,
         })
     }
@@ -1,34 +1,91 @@
 use super::{Segment, SegmentData};
+use crate::config::schema::{OptionDefault, OptionKind, OptionSpec};
 use crate::config::{InputData, SegmentId};
+use crate::core::template;
+use crate::utils::subscription_api::DEFAULT_CACHE_TTL_SECS;
+use crate::utils::SubscriptionApi;
 use std::collections::HashMap;
 
-#[derive(Default)]
-pub struct SubscriptionSegment;
+/// 默认显示模板（语法见 `core::template`），可通过 `format` / `secondary_format` 选项覆盖
+const DEFAULT_FORMAT: &str = "{group_name} | 今日:{daily_usd} 本周:{weekly_usd}/{weekly_limit_usd}";
+const DEFAULT_SECONDARY_FORMAT: &str =
+    "{?resets_in:刷新:{resets_in}}{?stale:{?resets_in: }(缓存:{cache_age_display}前)}";
+
+/// 可用选项（`--check`、JSON Schema 与 TUI 选项编辑器共用）
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "api_base_url",
+        kind: OptionKind::String,
+        default: OptionDefault::None,
+        description: "Relay API base URL; defaults to ANTHROPIC_BASE_URL",
+    },
+    OptionSpec {
+        name: "fallback_urls",
+        kind: OptionKind::StringList(None),
+        default: OptionDefault::None,
+        description: "Base URLs tried in order when the first fails",
+    },
+    OptionSpec {
+        name: "cache_duration",
+        kind: OptionKind::Integer {
+            min: 1,
+            max: 86_400,
+        },
+        default: OptionDefault::Integer(DEFAULT_CACHE_TTL_SECS),
+        description: "Seconds before the cached subscription info is refreshed",
+    },
+];
+
+pub struct SubscriptionSegment {
+    cache_duration: u64,
+    api_base_url: Option<String>,
+    fallback_urls: Vec<String>,
+}
+
+impl Default for SubscriptionSegment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl SubscriptionSegment {
     pub fn new() -> Self {
-        Self
+        Self {
+            cache_duration: DEFAULT_CACHE_TTL_SECS,
+            api_base_url: None,
+            fallback_urls: Vec::new(),
+        }
+    }
+
+    pub fn with_endpoints(
+        mut self,
+        api_base_url: Option<String>,
+        fallback_urls: Vec<String>,
+    ) -> Self {
+        self.api_base_url = api_base_url;
+        self.fallback_urls = fallback_urls;
+        self
+    }
+
+    pub fn with_cache_duration(mut self, cache_duration: u64) -> Self {
+        self.cache_duration = cache_duration;
+        self
     }
 }
 
 impl Segment for SubscriptionSegment {
     fn collect(&self, input: &InputData) -> Option<SegmentData> {
-        let subscription = input.subscription.as_ref()?;
-
-        // Primary display: 订阅名称 + 费用
-        let primary = format!(
-            "{} | 今日:{} 本周:{}/{}",
-            subscription.group_name,
-            format_usd(subscription.daily_used_usd),
-            format_usd(subscription.weekly_used_usd),
-            format_usd(subscription.weekly_limit_usd)
-        );
-
-        // Secondary display: 刷新时间
-        let secondary = if let Some(ref reset) = subscription.resets_in_seconds {
-            format!("刷新:{}", format_time_remaining(*reset))
-        } else {
-            String::new()
+        // Prefer data passed in on stdin, otherwise query the Sub2API usage endpoint
+        let fetched;
+        let (subscription, stale_age) = match input.subscription.as_ref() {
+            Some(subscription) => (subscription, None),
+            None => {
+                fetched = SubscriptionApi::load()?
+                    .with_endpoints(self.api_base_url.as_deref(), &self.fallback_urls)
+                    .get_subscription_info_cached(self.cache_duration)?;
+                let stale_age = fetched.stale.then_some(fetched.age_secs);
+                (&fetched.subscription, stale_age)
+            }
         };
 
         let mut metadata = HashMap::new();
@@ -45,10 +102,39 @@ impl Segment for SubscriptionSegment {
             "weekly_limit".to_string(),
             subscription.weekly_limit_usd.to_string(),
         );
+        // 周额度使用百分比，供颜色规则使用（如 weekly_percent >= 80）
+        if subscription.weekly_limit_usd > 0.0 {
+            let percent = subscription.weekly_used_usd / subscription.weekly_limit_usd * 100.0;
+            metadata.insert("weekly_percent".to_string(), format!("{:.1}", percent));
+        }
+
+        // 格式化后的显示值，供模板使用
+        metadata.insert(
+            "daily_usd".to_string(),
+            format_usd(subscription.daily_used_usd),
+        );
+        metadata.insert(
+            "weekly_usd".to_string(),
+            format_usd(subscription.weekly_used_usd),
+        );
+        metadata.insert(
+            "weekly_limit_usd".to_string(),
+            format_usd(subscription.weekly_limit_usd),
+        );
+        if let Some(reset) = subscription.resets_in_seconds {
+            metadata.insert("resets_in".to_string(), format_time_remaining(reset));
+        }
+
+        // 缓存过期时标注数据年龄（后台正在刷新）
+        if let Some(age) = stale_age {
+            metadata.insert("stale".to_string(), "true".to_string());
+            metadata.insert("cache_age".to_string(), age.to_string());
+            metadata.insert("cache_age_display".to_string(), format_age(age));
+        }
 
         Some(SegmentData {
-            primary,
-            secondary,
+            primary: template::render(DEFAULT_FORMAT, &metadata),
+            secondary: template::render(DEFAULT_SECONDARY_FORMAT, &metadata),
             metadata,
         })
     }
@@ -88,3 +174,13 @@ fn format_time_remaining(seconds: i64) -> String {
         format!("{}分钟", minutes)
     }
 }
+
+fn format_age(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{}秒", seconds)
+    } else if seconds < 3600 {
+        format!("{}分钟", seconds / 60)
+    } else {
+        format!("{}小时", seconds / 3600)
+    }
+}
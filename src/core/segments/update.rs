@@ -16,10 +16,14 @@ impl Segment for UpdateSegment {
         // Load update state and check for update status
         let update_state = UpdateState::load();
 
-        update_state.status_text().map(|status_text| SegmentData {
-            primary: status_text,
-            secondary: String::new(),
-            metadata: std::collections::HashMap::new(),
+        update_state.status_text().map(|status_text| {
+            let mut metadata = std::collections::HashMap::new();
+            metadata.insert("status_text".to_string(), status_text.clone());
+            SegmentData {
+                primary: status_text,
+                secondary: String::new(),
+                metadata,
+            }
         })
     }
 
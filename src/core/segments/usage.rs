@@ -1,17 +1,170 @@
 use super::{Segment, SegmentData};
-use crate::config::{InputData, SegmentId};
+use crate::config::schema::{OptionDefault, OptionKind, OptionSpec};
+use crate::config::{paths, InputData, SegmentId};
+use crate::core::template;
 use crate::utils::credentials;
-use chrono::{DateTime, Datelike, Duration, Local, Timelike, Utc};
+use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Current `ApiUsageCache` file format
+const CACHE_VERSION: u32 = 2;
+
+const DEFAULT_API_BASE_URL: &str = "https://api.anthropic.com";
+const DEFAULT_CACHE_DURATION_SECS: u64 = 300;
+const DEFAULT_TIMEOUT_SECS: u64 = 2;
+
+/// Default text templates (see `core::template`), overridable via the
+/// `format` / `secondary_format` options
+const DEFAULT_FORMAT: &str = "{percents}";
+const DEFAULT_SECONDARY_FORMAT: &str = "{?resets:· {resets}}";
+
+pub const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "windows",
+        kind: OptionKind::StringList(Some(&["five_hour", "seven_day"])),
+        default: OptionDefault::List(&["five_hour", "seven_day"]),
+        description: "Usage windows to show",
+    },
+    OptionSpec {
+        name: "reset_format",
+        kind: OptionKind::Enum(&["countdown", "time", "date"]),
+        default: OptionDefault::Str("countdown"),
+        description: "Reset time as a countdown, clock time or date",
+    },
+    OptionSpec {
+        name: "api_base_url",
+        kind: OptionKind::String,
+        default: OptionDefault::Str(DEFAULT_API_BASE_URL),
+        description: "Base URL of the OAuth usage API",
+    },
+    OptionSpec {
+        name: "oauth_token_url",
+        kind: OptionKind::String,
+        default: OptionDefault::Str(credentials::DEFAULT_TOKEN_URL),
+        description: "Endpoint used to refresh an expired OAuth token",
+    },
+    OptionSpec {
+        name: "cache_duration",
+        kind: OptionKind::Integer {
+            min: 1,
+            max: 86_400,
+        },
+        default: OptionDefault::Integer(DEFAULT_CACHE_DURATION_SECS),
+        description: "Seconds the usage response is reused",
+    },
+    OptionSpec {
+        name: "timeout",
+        kind: OptionKind::Integer { min: 1, max: 60 },
+        default: OptionDefault::Integer(DEFAULT_TIMEOUT_SECS),
+        description: "Request timeout in seconds",
+    },
+];
+
+/// A usage limit window reported by the OAuth usage API, selected with the
+/// `windows` option
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UsageWindow {
+    FiveHour,
+    SevenDay,
+}
+
+impl UsageWindow {
+    pub const ALL: [UsageWindow; 2] = [UsageWindow::FiveHour, UsageWindow::SevenDay];
+
+    /// Metadata key prefix and option value
+    pub fn key(self) -> &'static str {
+        match self {
+            UsageWindow::FiveHour => "five_hour",
+            UsageWindow::SevenDay => "seven_day",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            UsageWindow::FiveHour => "5h",
+            UsageWindow::SevenDay => "7d",
+        }
+    }
+
+    /// Windows listed in the `windows` option, in order; both windows when
+    /// the option is missing or names none
+    pub fn list_from_option(value: Option<&serde_json::Value>) -> Vec<Self> {
+        let windows: Vec<Self> = value
+            .and_then(|v| v.as_array())
+            .map(|names| {
+                let mut windows = Vec::new();
+                for name in names.iter().filter_map(|v| v.as_str()) {
+                    let window = Self::ALL.into_iter().find(|w| w.key() == name);
+                    if let Some(window) = window.filter(|w| !windows.contains(w)) {
+                        windows.push(window);
+                    }
+                }
+                windows
+            })
+            .unwrap_or_default();
+        if windows.is_empty() {
+            Self::ALL.to_vec()
+        } else {
+            windows
+        }
+    }
+}
+
+/// How reset times are shown, selected with the `reset_format` option
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ResetFormat {
+    /// Time left until the reset (`2h13m`, `3d4h`)
+    #[default]
+    Countdown,
+    /// Local clock time (`14:30`), with the weekday when not today
+    Time,
+    /// Local date and hour (`Oct 7 14:00`)
+    Date,
+}
+
+impl ResetFormat {
+    pub fn from_option(value: Option<&str>) -> Self {
+        match value {
+            Some("time") => ResetFormat::Time,
+            Some("date") => ResetFormat::Date,
+            _ => ResetFormat::Countdown,
+        }
+    }
+
+    pub fn format(self, resets_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        let local = resets_at.with_timezone(&Local);
+        match self {
+            ResetFormat::Countdown => {
+                let minutes = (resets_at - now).num_minutes().max(0);
+                let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+                if days > 0 {
+                    format!("{}d{}h", days, hours)
+                } else if hours > 0 {
+                    format!("{}h{}m", hours, minutes)
+                } else {
+                    format!("{}m", minutes)
+                }
+            }
+            ResetFormat::Time => {
+                if local.date_naive() == now.with_timezone(&Local).date_naive() {
+                    local.format("%H:%M").to_string()
+                } else {
+                    local.format("%a %H:%M").to_string()
+                }
+            }
+            ResetFormat::Date => local.format("%b %-d %H:%M").to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiUsageResponse {
     five_hour: UsagePeriod,
     seven_day: UsagePeriod,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct UsagePeriod {
     utilization: f64,
     resets_at: Option<String>,
@@ -19,18 +172,105 @@ struct UsagePeriod {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ApiUsageCache {
+    version: u32,
+    five_hour: UsagePeriod,
+    seven_day: UsagePeriod,
+    cached_at: String,
+}
+
+impl ApiUsageCache {
+    fn window(&self, window: UsageWindow) -> &UsagePeriod {
+        match window {
+            UsageWindow::FiveHour => &self.five_hour,
+            UsageWindow::SevenDay => &self.seven_day,
+        }
+    }
+}
+
+/// Cache written before windows were stored separately. Its `resets_at`
+/// belonged to the 7-day window; the 5-hour reset was never saved.
+#[derive(Debug, Deserialize)]
+struct LegacyApiUsageCache {
     five_hour_utilization: f64,
     seven_day_utilization: f64,
     resets_at: Option<String>,
     cached_at: String,
 }
 
-#[derive(Default)]
-pub struct UsageSegment;
+impl From<LegacyApiUsageCache> for ApiUsageCache {
+    fn from(legacy: LegacyApiUsageCache) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            five_hour: UsagePeriod {
+                utilization: legacy.five_hour_utilization,
+                resets_at: None,
+            },
+            seven_day: UsagePeriod {
+                utilization: legacy.seven_day_utilization,
+                resets_at: legacy.resets_at,
+            },
+            cached_at: legacy.cached_at,
+        }
+    }
+}
+
+pub struct UsageSegment {
+    windows: Vec<UsageWindow>,
+    reset_format: ResetFormat,
+    api_base_url: String,
+    token_url: String,
+    cache_duration: u64,
+    timeout: u64,
+}
+
+impl Default for UsageSegment {
+    fn default() -> Self {
+        Self {
+            windows: UsageWindow::ALL.to_vec(),
+            reset_format: ResetFormat::default(),
+            api_base_url: DEFAULT_API_BASE_URL.to_string(),
+            token_url: credentials::token_url(None),
+            cache_duration: DEFAULT_CACHE_DURATION_SECS,
+            timeout: DEFAULT_TIMEOUT_SECS,
+        }
+    }
+}
 
 impl UsageSegment {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn with_windows(mut self, windows: Vec<UsageWindow>) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    pub fn with_reset_format(mut self, reset_format: ResetFormat) -> Self {
+        self.reset_format = reset_format;
+        self
+    }
+
+    pub fn with_api_base_url(mut self, api_base_url: String) -> Self {
+        self.api_base_url = api_base_url;
+        self
+    }
+
+    /// Endpoint used to refresh an expired OAuth token (see
+    /// `credentials::token_url`)
+    pub fn with_token_url(mut self, token_url: String) -> Self {
+        self.token_url = token_url;
+        self
+    }
+
+    pub fn with_cache_duration(mut self, seconds: u64) -> Self {
+        self.cache_duration = seconds;
+        self
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout = seconds;
+        self
     }
 
     fn get_circle_icon(utilization: f64) -> String {
@@ -47,33 +287,51 @@ impl UsageSegment {
         }
     }
 
-    fn format_reset_time(reset_time_str: Option<&str>) -> String {
-        if let Some(time_str) = reset_time_str {
-            if let Ok(dt) = DateTime::parse_from_rfc3339(time_str) {
-                let mut local_dt = dt.with_timezone(&Local);
-                if local_dt.minute() > 45 {
-                    local_dt += Duration::hours(1);
-                }
-                return format!(
-                    "{}-{}-{}",
-                    local_dt.month(),
-                    local_dt.day(),
-                    local_dt.hour()
-                );
+    /// Add the `percents` (`5h 24% · 7d 61%`) and `resets`
+    /// (`5h 2h13m / 7d 3d4h`) summaries of the selected windows to the
+    /// per-window metadata. Labels are only shown when more than one window
+    /// is, and windows without a reset time (an idle 5-hour window) are left
+    /// out of `resets`.
+    pub fn insert_summary(&self, metadata: &mut HashMap<String, String>) {
+        let labelled = self.windows.len() > 1;
+        let with_label = |window: UsageWindow, text: String| {
+            if labelled {
+                format!("{} {}", window.label(), text)
+            } else {
+                text
             }
+        };
+
+        let mut percents = Vec::new();
+        let mut resets = Vec::new();
+        for &window in &self.windows {
+            let key = window.key();
+            if let Some(percent) = metadata.get(&format!("{}_percent", key)) {
+                percents.push(with_label(window, format!("{}%", percent)));
+            }
+            if let Some(reset) = metadata.get(&format!("{}_reset", key)) {
+                resets.push(with_label(window, reset.clone()));
+            }
+        }
+
+        metadata.insert("percents".to_string(), percents.join(" · "));
+        if !resets.is_empty() {
+            metadata.insert("resets".to_string(), resets.join(" / "));
         }
-        "?".to_string()
     }
 
-    fn get_cache_path() -> Option<std::path::PathBuf> {
-        let home = dirs::home_dir()?;
-        Some(
-            home.join(".claude")
-                .join("micusubcodeline")
-                .join(".api_usage_cache.json"),
+    /// Primary and secondary text from the default templates
+    pub fn default_text(metadata: &HashMap<String, String>) -> (String, String) {
+        (
+            template::render(DEFAULT_FORMAT, metadata),
+            template::render(DEFAULT_SECONDARY_FORMAT, metadata),
         )
     }
 
+    fn get_cache_path() -> Option<std::path::PathBuf> {
+        Some(paths::cache_dir()?.join(".api_usage_cache.json"))
+    }
+
     fn load_cache(&self) -> Option<ApiUsageCache> {
         let cache_path = Self::get_cache_path()?;
         if !cache_path.exists() {
@@ -81,7 +339,14 @@ impl UsageSegment {
         }
 
         let content = std::fs::read_to_string(&cache_path).ok()?;
-        serde_json::from_str(&content).ok()
+        let value: serde_json::Value = serde_json::from_str(&content).ok()?;
+        if value.get("version").is_some() {
+            serde_json::from_value(value).ok()
+        } else {
+            serde_json::from_value::<LegacyApiUsageCache>(value)
+                .ok()
+                .map(ApiUsageCache::from)
+        }
     }
 
     fn save_cache(&self, cache: &ApiUsageCache) {
@@ -90,7 +355,14 @@ impl UsageSegment {
                 let _ = std::fs::create_dir_all(parent);
             }
             if let Ok(json) = serde_json::to_string_pretty(cache) {
-                let _ = std::fs::write(&cache_path, json);
+                // Temp file + rename so concurrent statusline processes never
+                // read a partial cache
+                let tmp_path = cache_path.with_extension(format!("json.{}", std::process::id()));
+                if std::fs::write(&tmp_path, json).is_ok()
+                    && std::fs::rename(&tmp_path, &cache_path).is_err()
+                {
+                    let _ = std::fs::remove_file(&tmp_path);
+                }
             }
         }
     }
@@ -126,10 +398,7 @@ impl UsageSegment {
     }
 
     fn get_proxy_from_settings() -> Option<String> {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .ok()?;
-        let settings_path = format!("{}/.claude/settings.json", home);
+        let settings_path = paths::claude_dir()?.join("settings.json");
 
         let content = std::fs::read_to_string(&settings_path).ok()?;
         let settings: serde_json::Value = serde_json::from_str(&content).ok()?;
@@ -182,86 +451,84 @@ impl UsageSegment {
 
 impl Segment for UsageSegment {
     fn collect(&self, _input: &InputData) -> Option<SegmentData> {
-        let token = credentials::get_oauth_token()?;
-
-        // Load config from file to get segment options
-        let config = crate::config::Config::load().ok()?;
-        let segment_config = config.segments.iter().find(|s| s.id == SegmentId::Usage);
-
-        let api_base_url = segment_config
-            .and_then(|sc| sc.options.get("api_base_url"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("https://api.anthropic.com");
-
-        let cache_duration = segment_config
-            .and_then(|sc| sc.options.get("cache_duration"))
-            .and_then(|v| v.as_u64())
-            .unwrap_or(300);
-
-        let timeout = segment_config
-            .and_then(|sc| sc.options.get("timeout"))
-            .and_then(|v| v.as_u64())
-            .unwrap_or(2);
+        // Without a usable token (e.g. while it is refreshed in the
+        // background) the cached usage is still shown
+        let token = credentials::get_oauth_token_with(&self.token_url);
 
         let cached_data = self.load_cache();
         let use_cached = cached_data
             .as_ref()
-            .map(|cache| self.is_cache_valid(cache, cache_duration))
+            .map(|cache| self.is_cache_valid(cache, self.cache_duration))
             .unwrap_or(false);
 
-        let (five_hour_util, seven_day_util, resets_at) = if use_cached {
-            let cache = cached_data.unwrap();
-            (
-                cache.five_hour_utilization,
-                cache.seven_day_utilization,
-                cache.resets_at,
-            )
+        let usage = if use_cached {
+            cached_data?
         } else {
-            match self.fetch_api_usage(api_base_url, &token, timeout) {
+            let response = token
+                .as_deref()
+                .and_then(|token| self.fetch_api_usage(&self.api_base_url, token, self.timeout));
+            match response {
                 Some(response) => {
                     let cache = ApiUsageCache {
-                        five_hour_utilization: response.five_hour.utilization,
-                        seven_day_utilization: response.seven_day.utilization,
-                        resets_at: response.seven_day.resets_at.clone(),
+                        version: CACHE_VERSION,
+                        five_hour: response.five_hour,
+                        seven_day: response.seven_day,
                         cached_at: Utc::now().to_rfc3339(),
                     };
                     self.save_cache(&cache);
-                    (
-                        response.five_hour.utilization,
-                        response.seven_day.utilization,
-                        response.seven_day.resets_at,
-                    )
-                }
-                None => {
-                    if let Some(cache) = cached_data {
-                        (
-                            cache.five_hour_utilization,
-                            cache.seven_day_utilization,
-                            cache.resets_at,
-                        )
-                    } else {
-                        return None;
-                    }
+                    cache
                 }
+                None => cached_data?,
             }
         };
 
-        let dynamic_icon = Self::get_circle_icon(seven_day_util / 100.0);
-        let five_hour_percent = five_hour_util.round() as u8;
-        let primary = format!("{}%", five_hour_percent);
-        let secondary = format!("· {}", Self::format_reset_time(resets_at.as_deref()));
-
+        let now = Utc::now();
         let mut metadata = HashMap::new();
-        metadata.insert("dynamic_icon".to_string(), dynamic_icon);
-        metadata.insert(
-            "five_hour_utilization".to_string(),
-            five_hour_util.to_string(),
-        );
+        for window in UsageWindow::ALL {
+            let key = window.key();
+            let period = usage.window(window);
+            metadata.insert(
+                format!("{}_utilization", key),
+                period.utilization.to_string(),
+            );
+            metadata.insert(
+                format!("{}_percent", key),
+                (period.utilization.round() as u8).to_string(),
+            );
+
+            let resets_at = period
+                .resets_at
+                .as_deref()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc));
+            if let Some(resets_at) = resets_at {
+                metadata.insert(format!("{}_resets_at", key), resets_at.to_rfc3339());
+                metadata.insert(
+                    format!("{}_reset", key),
+                    self.reset_format.format(resets_at, now),
+                );
+            }
+        }
+
+        // The icon and `reset_time` follow the displayed window closest to
+        // its limit
+        let busiest = self.windows.iter().copied().max_by(|a, b| {
+            let (a, b) = (usage.window(*a), usage.window(*b));
+            a.utilization.total_cmp(&b.utilization)
+        })?;
         metadata.insert(
-            "seven_day_utilization".to_string(),
-            seven_day_util.to_string(),
+            "dynamic_icon".to_string(),
+            Self::get_circle_icon(usage.window(busiest).utilization / 100.0),
         );
+        let reset_time = std::iter::once(busiest)
+            .chain(self.windows.iter().copied())
+            .find_map(|window| metadata.get(&format!("{}_reset", window.key())).cloned());
+        if let Some(reset_time) = reset_time {
+            metadata.insert("reset_time".to_string(), reset_time);
+        }
 
+        self.insert_summary(&mut metadata);
+        let (primary, secondary) = Self::default_text(&metadata);
         Some(SegmentData {
             primary,
             secondary,
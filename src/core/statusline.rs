@@ -1,5 +1,16 @@
-use crate::config::{AnsiColor, Config, SegmentConfig, StyleMode};
-use crate::core::segments::SegmentData;
+use crate::config::{
+    AnsiColor, ColorConfig, Config, InputData, SegmentConfig, SegmentId, StyleConfig, StyleMode,
+};
+use crate::core::backends::{self, Markup, ResolvedSegment};
+use crate::core::cache::SegmentCache;
+use crate::core::segments::*;
+use crate::core::template::apply_segment_format;
+use crate::utils::{credentials, SubscriptionApi};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 use unicode_width::UnicodeWidthChar;
 
 /// Strip ANSI escape sequences and return visible display width
@@ -31,6 +42,28 @@ fn visible_width(text: &str) -> usize {
     width
 }
 
+/// A collected segment with its resolved icon and colors, as emitted by
+/// `--format json`
+#[derive(Debug, Serialize)]
+pub struct SegmentOutput {
+    pub id: SegmentId,
+    /// Name of a custom segment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub icon: String,
+    pub primary: String,
+    pub secondary: String,
+    pub metadata: BTreeMap<String, String>,
+    pub colors: ColorConfig,
+    pub text_bold: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusLineOutput {
+    pub style: StyleConfig,
+    pub segments: Vec<SegmentOutput>,
+}
+
 pub struct StatusLineGenerator {
     config: Config,
 }
@@ -40,12 +73,22 @@ impl StatusLineGenerator {
         Self { config }
     }
 
-    pub fn generate(&self, segments: Vec<(SegmentConfig, SegmentData)>) -> String {
-        let mut output = Vec::new();
-        let enabled_segments: Vec<_> = segments
+    /// Drop disabled segments and apply each segment's color rules, so the
+    /// renderer and powerline transitions see the resolved colors
+    fn resolve_enabled(
+        &self,
+        segments: Vec<(SegmentConfig, SegmentData)>,
+    ) -> Vec<(SegmentConfig, SegmentData)> {
+        segments
             .into_iter()
             .filter(|(config, _)| config.enabled)
-            .collect();
+            .map(|(config, data)| (config.with_color_rules_applied(&data.metadata), data))
+            .collect()
+    }
+
+    pub fn generate(&self, segments: Vec<(SegmentConfig, SegmentData)>) -> String {
+        let mut output = Vec::new();
+        let enabled_segments: Vec<_> = self.resolve_enabled(segments);
 
         for (config, data) in enabled_segments.iter() {
             let rendered = self.render_segment(config, data);
@@ -67,6 +110,74 @@ impl StatusLineGenerator {
         }
     }
 
+    /// Structured form of the statusline: every enabled segment's data with
+    /// color rules applied, for consumers that render it themselves
+    pub fn generate_json(&self, segments: Vec<(SegmentConfig, SegmentData)>) -> String {
+        let segments = self
+            .resolve_enabled(segments)
+            .into_iter()
+            .map(|(config, data)| SegmentOutput {
+                id: config.id,
+                name: config.custom_name().map(str::to_string),
+                icon: self.resolve_icon(&config, &data),
+                primary: data.primary,
+                secondary: data.secondary,
+                metadata: data.metadata.into_iter().collect(),
+                colors: config.colors,
+                text_bold: config.styles.text_bold,
+            })
+            .collect();
+
+        let output = StatusLineOutput {
+            style: self.config.style.clone(),
+            segments,
+        };
+        serde_json::to_string(&output).unwrap_or_default()
+    }
+
+    /// Render the statusline as tmux, zsh, bash or Pango markup
+    pub fn generate_markup(
+        &self,
+        markup: Markup,
+        segments: Vec<(SegmentConfig, SegmentData)>,
+    ) -> String {
+        markup.render(
+            &self.config.style.separator,
+            &self.resolve_for_backend(segments),
+        )
+    }
+
+    /// Render the statusline as an array of i3bar blocks
+    pub fn generate_i3bar(&self, segments: Vec<(SegmentConfig, SegmentData)>) -> String {
+        backends::render_i3bar(
+            &self.config.style.separator,
+            &self.resolve_for_backend(segments),
+        )
+    }
+
+    /// Render the statusline as waybar custom module JSON
+    pub fn generate_waybar(&self, segments: Vec<(SegmentConfig, SegmentData)>) -> String {
+        backends::render_waybar(
+            &self.config.style.separator,
+            &self.resolve_for_backend(segments),
+        )
+    }
+
+    fn resolve_for_backend(
+        &self,
+        segments: Vec<(SegmentConfig, SegmentData)>,
+    ) -> Vec<ResolvedSegment> {
+        self.resolve_enabled(segments)
+            .into_iter()
+            .filter(|(_, data)| !data.primary.is_empty())
+            .map(|(config, data)| ResolvedSegment {
+                icon: self.resolve_icon(&config, &data),
+                config,
+                data,
+            })
+            .collect()
+    }
+
     /// Generate statusline with intelligent wrapping based on terminal width.
     /// Returns multiple lines if the content exceeds max_width.
     pub fn generate_wrapped(
@@ -75,10 +186,7 @@ impl StatusLineGenerator {
         max_width: usize,
     ) -> Vec<String> {
         let mut rendered_segments = Vec::new();
-        let enabled_segments: Vec<_> = segments
-            .into_iter()
-            .filter(|(config, _)| config.enabled)
-            .collect();
+        let enabled_segments: Vec<_> = self.resolve_enabled(segments);
 
         for (config, data) in enabled_segments.iter() {
             let rendered = self.render_segment(config, data);
@@ -209,10 +317,7 @@ impl StatusLineGenerator {
         use ansi_to_tui::IntoText;
         use ratatui::text::{Line, Span, Text};
 
-        let enabled_segments: Vec<_> = segments
-            .into_iter()
-            .filter(|(config, _)| config.enabled)
-            .collect();
+        let enabled_segments: Vec<_> = self.resolve_enabled(segments);
 
         if enabled_segments.is_empty() {
             return Text::from(vec![Line::default()]);
@@ -327,11 +432,7 @@ impl StatusLineGenerator {
     }
 
     fn render_segment(&self, config: &SegmentConfig, data: &SegmentData) -> String {
-        let icon = if let Some(dynamic_icon) = data.metadata.get("dynamic_icon") {
-            dynamic_icon.clone()
-        } else {
-            self.get_icon(config)
-        };
+        let icon = self.resolve_icon(config, data);
 
         // Apply background color to the entire segment if set
         if let Some(bg_color) = &config.colors.background {
@@ -394,6 +495,14 @@ impl StatusLineGenerator {
         }
     }
 
+    /// The segment's icon, unless its data supplies a `dynamic_icon`
+    fn resolve_icon(&self, config: &SegmentConfig, data: &SegmentData) -> String {
+        match data.metadata.get("dynamic_icon") {
+            Some(dynamic_icon) => dynamic_icon.clone(),
+            None => self.get_icon(config),
+        }
+    }
+
     fn get_icon(&self, config: &SegmentConfig) -> String {
         match self.config.style.mode {
             StyleMode::Plain => config.icon.plain.clone(),
@@ -566,72 +675,246 @@ impl StatusLineGenerator {
     }
 }
 
-pub fn collect_all_segments(
-    config: &Config,
-    input: &crate::config::InputData,
-) -> Vec<(SegmentConfig, SegmentData)> {
-    use crate::core::segments::*;
+/// Default time budget for segments that only read local state
+const DEFAULT_DEADLINE_MS: u64 = 1000;
 
-    let mut results = Vec::new();
+/// Default time budget for segments that call a remote API
+const DEFAULT_NETWORK_DEADLINE_MS: u64 = 3000;
 
-    for segment_config in &config.segments {
-        // Skip disabled segments to avoid unnecessary API requests
-        if !segment_config.enabled {
-            continue;
-        }
+/// Time budget for a segment without a `deadline_ms` option
+pub fn default_deadline_ms(id: SegmentId) -> u64 {
+    match id {
+        SegmentId::Usage | SegmentId::Subscription => DEFAULT_NETWORK_DEADLINE_MS,
+        _ => DEFAULT_DEADLINE_MS,
+    }
+}
 
-        let segment_data = match segment_config.id {
-            crate::config::SegmentId::Model => {
-                let segment = ModelSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Directory => {
-                let segment = DirectorySegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Git => {
-                let show_sha = segment_config
+/// Get the time budget for a segment from its `deadline_ms` option
+fn segment_deadline(segment_config: &SegmentConfig) -> Duration {
+    let ms = segment_config
+        .options
+        .get("deadline_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or_else(|| default_deadline_ms(segment_config.id));
+    Duration::from_millis(ms)
+}
+
+/// Build the segment collector for a segment config
+fn create_segment(segment_config: &SegmentConfig) -> Option<Box<dyn Segment>> {
+    let segment: Box<dyn Segment> = match segment_config.id {
+        SegmentId::Model => Box::new(ModelSegment::new()),
+        SegmentId::Directory => Box::new(DirectorySegment::from_options(&segment_config.options)),
+        SegmentId::Git => {
+            let show_sha = segment_config
+                .options
+                .get("show_sha")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let backend = GitBackend::from_option(
+                segment_config
                     .options
-                    .get("show_sha")
+                    .get("backend")
+                    .and_then(|v| v.as_str()),
+            );
+            let mut segment = GitSegment::new()
+                .with_sha(show_sha)
+                .with_backend(backend)
+                .with_glyphs(GitGlyphs::from_options(&segment_config.options))
+                .with_diff(DiffSettings::from_options(&segment_config.options));
+            if let Some(timeout_ms) = segment_config
+                .options
+                .get("status_timeout_ms")
+                .and_then(|v| v.as_u64())
+            {
+                segment = segment.with_status_timeout(Duration::from_millis(timeout_ms));
+            }
+            Box::new(segment)
+        }
+        SegmentId::ContextWindow => {
+            let option_flag = |key: &str| {
+                segment_config
+                    .options
+                    .get(key)
                     .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                let segment = GitSegment::new().with_sha(show_sha);
-                segment.collect(input)
-            }
-            crate::config::SegmentId::ContextWindow => {
-                let segment = ContextWindowSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Usage => {
-                let segment = UsageSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Cost => {
-                let segment = CostSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Session => {
-                let segment = SessionSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::OutputStyle => {
-                let segment = OutputStyleSegment::new();
-                segment.collect(input)
-            }
-            crate::config::SegmentId::Update => {
-                let segment = UpdateSegment::new();
-                segment.collect(input)
+                    .unwrap_or(false)
+            };
+            Box::new(
+                ContextWindowSegment::new()
+                    .with_breakdown(option_flag("breakdown"))
+                    .with_headroom(option_flag("headroom")),
+            )
+        }
+        SegmentId::Usage => {
+            let windows = UsageWindow::list_from_option(segment_config.options.get("windows"));
+            let reset_format = ResetFormat::from_option(
+                segment_config
+                    .options
+                    .get("reset_format")
+                    .and_then(|v| v.as_str()),
+            );
+            let mut segment = UsageSegment::new()
+                .with_windows(windows)
+                .with_reset_format(reset_format)
+                .with_token_url(credentials::token_url(
+                    segment_config
+                        .options
+                        .get("oauth_token_url")
+                        .and_then(|v| v.as_str()),
+                ));
+            if let Some(api_base_url) = segment_config
+                .options
+                .get("api_base_url")
+                .and_then(|v| v.as_str())
+            {
+                segment = segment.with_api_base_url(api_base_url.to_string());
+            }
+            if let Some(cache_duration) = segment_config
+                .options
+                .get("cache_duration")
+                .and_then(|v| v.as_u64())
+            {
+                segment = segment.with_cache_duration(cache_duration);
+            }
+            if let Some(timeout) = segment_config
+                .options
+                .get("timeout")
+                .and_then(|v| v.as_u64())
+            {
+                segment = segment.with_timeout(timeout);
+            }
+            Box::new(segment)
+        }
+        SegmentId::Cost => {
+            let estimate = CostEstimate::from_option(
+                segment_config
+                    .options
+                    .get("estimate")
+                    .and_then(|v| v.as_str()),
+            );
+            Box::new(CostSegment::new().with_estimate(estimate))
+        }
+        SegmentId::Session => Box::new(SessionSegment::new()),
+        SegmentId::OutputStyle => Box::new(OutputStyleSegment::new()),
+        SegmentId::Update => Box::new(UpdateSegment::new()),
+        SegmentId::Subscription => {
+            let (api_base_url, fallback_urls) =
+                SubscriptionApi::endpoint_options(&segment_config.options);
+            let mut segment =
+                SubscriptionSegment::new().with_endpoints(api_base_url, fallback_urls);
+            if let Some(cache_duration) = segment_config
+                .options
+                .get("cache_duration")
+                .and_then(|v| v.as_u64())
+            {
+                segment = segment.with_cache_duration(cache_duration);
+            }
+            Box::new(segment)
+        }
+        SegmentId::Custom => Box::new(CustomSegment::from_options(&segment_config.options)?),
+    };
+    Some(segment)
+}
+
+/// Outcome of collecting a single segment within its time budget
+enum Collected {
+    Fresh(Option<SegmentData>),
+    TimedOut,
+}
+
+/// Collect all enabled segments concurrently.
+///
+/// Each segment runs on its own thread with a time budget (`deadline_ms`
+/// option). A segment that misses its deadline renders from the last cached
+/// value (marked with `cached` metadata) or is skipped if nothing is cached.
+pub fn collect_all_segments(
+    config: &Config,
+    input: &InputData,
+) -> Vec<(SegmentConfig, SegmentData)> {
+    // Skip disabled segments to avoid unnecessary API requests
+    let enabled: Vec<&SegmentConfig> = config.segments.iter().filter(|s| s.enabled).collect();
+    if enabled.is_empty() {
+        return Vec::new();
+    }
+
+    let start = Instant::now();
+    let shared_input = Arc::new(input.clone());
+    let (tx, rx) = mpsc::channel();
+
+    for (index, segment_config) in enabled.iter().enumerate() {
+        let tx = tx.clone();
+        let input = Arc::clone(&shared_input);
+        let segment_config = (*segment_config).clone();
+        // Threads that miss their deadline are left detached; the process
+        // exits right after rendering so they never outlive the statusline
+        thread::spawn(move || {
+            let data = create_segment(&segment_config).and_then(|segment| segment.collect(&input));
+            let _ = tx.send((index, data));
+        });
+    }
+    drop(tx);
+
+    let deadlines: Vec<Instant> = enabled
+        .iter()
+        .map(|segment_config| start + segment_deadline(segment_config))
+        .collect();
+    let mut outcomes: Vec<Collected> = enabled.iter().map(|_| Collected::TimedOut).collect();
+    let mut pending: Vec<bool> = vec![true; enabled.len()];
+
+    loop {
+        let now = Instant::now();
+        let next_deadline = (0..enabled.len())
+            .filter(|&i| pending[i] && deadlines[i] > now)
+            .map(|i| deadlines[i])
+            .min();
+
+        let Some(next_deadline) = next_deadline else {
+            break;
+        };
+
+        match rx.recv_timeout(next_deadline - now) {
+            Ok((index, data)) => {
+                // Results arriving after the segment's own deadline are dropped
+                if pending[index] && Instant::now() <= deadlines[index] {
+                    outcomes[index] = Collected::Fresh(data);
+                }
+                pending[index] = false;
             }
-            crate::config::SegmentId::Subscription => {
-                let segment = SubscriptionSegment::new();
-                segment.collect(input)
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                for i in 0..enabled.len() {
+                    if deadlines[i] <= now {
+                        pending[i] = false;
+                    }
+                }
             }
-        };
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let mut cache = SegmentCache::load();
+    let mut results = Vec::new();
 
-        if let Some(data) = segment_data {
-            results.push((segment_config.clone(), data));
+    for (segment_config, outcome) in enabled.into_iter().zip(outcomes) {
+        let key = SegmentCache::key_for(segment_config, input);
+        match outcome {
+            Collected::Fresh(Some(mut data)) => {
+                cache.insert(key, data.clone());
+                apply_segment_format(segment_config, &mut data);
+                results.push((segment_config.clone(), data));
+            }
+            Collected::Fresh(None) => cache.remove(&key),
+            Collected::TimedOut => {
+                if let Some(cached) = cache.get(&key) {
+                    let mut data = cached.clone();
+                    data.metadata
+                        .insert("cached".to_string(), "true".to_string());
+                    apply_segment_format(segment_config, &mut data);
+                    results.push((segment_config.clone(), data));
+                }
+            }
         }
     }
 
+    cache.save();
     results
 }
@@ -0,0 +1,298 @@
+//! Small template language for segment text.
+//!
+//! - `{key}` inserts a metadata value (empty if missing)
+//! - `{key:<10}` / `{key:>10}` / `{key:^10}` pads to a display width (at
+//!   most `MAX_PAD_WIDTH` columns)
+//! - `{key:.8}` truncates to 8 columns with an ellipsis; combine as `{key:>6.4}`
+//! - `{?key:text}` renders `text` only if `key` is set and not empty, `0`,
+//!   `false` or `-`; `{!key:text}` renders it only if it is not. `text` may
+//!   contain further placeholders.
+//! - `{{` and `}}` insert literal braces, also inside a conditional body.
+//!   A run of closing braces there first closes the open conditionals
+//!   (`{?a:{?b:x}}` closes both), keeping pairs for literals: `{?a:x}}}`
+//!   renders `x}`.
+
+use crate::config::SegmentConfig;
+use crate::core::segments::SegmentData;
+use std::collections::HashMap;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Upper bound for a padding width, so a typo like `{key:>99999999}` can't
+/// allocate a huge string on every render
+pub const MAX_PAD_WIDTH: usize = 256;
+
+/// Render a template against a set of values
+pub fn render(template: &str, values: &HashMap<String, String>) -> String {
+    let chars: Vec<char> = template.chars().collect();
+    let mut pos = 0;
+    render_until(&chars, &mut pos, values, 0)
+}
+
+/// Apply a segment's `format` / `secondary_format` options to collected data.
+///
+/// `format` replaces the primary text and, unless `secondary_format` is also
+/// set, clears the secondary text. Templates can reference every metadata
+/// key plus `primary` and `secondary` (the segment's default text).
+pub fn apply_segment_format(config: &SegmentConfig, data: &mut SegmentData) {
+    let format = config.options.get("format").and_then(|v| v.as_str());
+    let secondary_format = config
+        .options
+        .get("secondary_format")
+        .and_then(|v| v.as_str());
+
+    if format.is_none() && secondary_format.is_none() {
+        return;
+    }
+
+    let mut values = data.metadata.clone();
+    values.insert("primary".to_string(), data.primary.clone());
+    values.insert("secondary".to_string(), data.secondary.clone());
+
+    if let Some(format) = format {
+        data.primary = render(format, &values);
+        data.secondary = String::new();
+    }
+    if let Some(secondary_format) = secondary_format {
+        data.secondary = render(secondary_format, &values);
+    }
+}
+
+/// Whether a value counts as "set" for `{?key:...}` conditionals
+fn is_truthy(value: Option<&String>) -> bool {
+    match value.map(|v| v.trim()) {
+        None | Some("") | Some("-") | Some("false") => false,
+        Some(v) => v.parse::<f64>().map(|n| n != 0.0).unwrap_or(true),
+    }
+}
+
+/// Render until the end of input, or until the closing `}` of a
+/// conditional body. `depth` is the number of conditionals open here.
+fn render_until(
+    chars: &[char],
+    pos: &mut usize,
+    values: &HashMap<String, String>,
+    depth: usize,
+) -> String {
+    let mut out = String::new();
+
+    while *pos < chars.len() {
+        let c = chars[*pos];
+        let next = chars.get(*pos + 1).copied();
+
+        match c {
+            '{' if next == Some('{') => {
+                out.push('{');
+                *pos += 2;
+            }
+            '}' if depth > 0 => {
+                // Close as many conditionals as the run allows; the rest
+                // must pair up as `}}` escapes
+                let run = chars[*pos..].iter().take_while(|&&c| c == '}').count();
+                let mut closing = run.min(depth);
+                if (run - closing) % 2 == 1 {
+                    closing -= 1;
+                }
+                for _ in 0..(run - closing) / 2 {
+                    out.push('}');
+                }
+                *pos += run - closing;
+                if closing > 0 {
+                    *pos += 1;
+                    return out;
+                }
+            }
+            '}' if next == Some('}') => {
+                out.push('}');
+                *pos += 2;
+            }
+            '{' => {
+                *pos += 1;
+                out.push_str(&render_placeholder(chars, pos, values, depth));
+            }
+            _ => {
+                out.push(c);
+                *pos += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// Render a placeholder; `pos` points just past its opening `{`
+fn render_placeholder(
+    chars: &[char],
+    pos: &mut usize,
+    values: &HashMap<String, String>,
+    depth: usize,
+) -> String {
+    match chars.get(*pos) {
+        Some(&marker @ ('?' | '!')) => {
+            *pos += 1;
+            let key = read_while(chars, pos, |c| c != ':' && c != '}');
+            if chars.get(*pos) == Some(&':') {
+                *pos += 1;
+            }
+            let body = render_until(chars, pos, values, depth + 1);
+            let show = is_truthy(values.get(key.trim())) == (marker == '?');
+            if show {
+                body
+            } else {
+                String::new()
+            }
+        }
+        _ => {
+            let inner = read_while(chars, pos, |c| c != '}');
+            *pos += 1; // closing brace
+
+            let (key, spec) = match inner.split_once(':') {
+                Some((key, spec)) => (key.trim(), Some(spec)),
+                None => (inner.trim(), None),
+            };
+            let value = values.get(key).cloned().unwrap_or_default();
+            match spec {
+                Some(spec) => apply_spec(&value, spec),
+                None => value,
+            }
+        }
+    }
+}
+
+fn read_while(chars: &[char], pos: &mut usize, pred: impl Fn(char) -> bool) -> String {
+    let start = *pos;
+    while *pos < chars.len() && pred(chars[*pos]) {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+/// Apply a `[<>^][width][.max]` spec
+fn apply_spec(value: &str, spec: &str) -> String {
+    let mut rest = spec.trim();
+    let align = match rest.chars().next() {
+        Some(a @ ('<' | '>' | '^')) => {
+            rest = &rest[1..];
+            a
+        }
+        _ => '<',
+    };
+
+    let (width_part, max_part) = match rest.split_once('.') {
+        Some((w, m)) => (w, Some(m)),
+        None => (rest, None),
+    };
+    let width = width_part.parse().unwrap_or(0).min(MAX_PAD_WIDTH);
+    let max: Option<usize> = max_part.and_then(|m| m.parse().ok());
+
+    let text = match max {
+        Some(max) => truncate(value, max),
+        None => value.to_string(),
+    };
+
+    let text_width = text.width();
+    if text_width >= width {
+        return text;
+    }
+
+    let pad = width - text_width;
+    match align {
+        '>' => format!("{}{}", " ".repeat(pad), text),
+        '^' => format!(
+            "{}{}{}",
+            " ".repeat(pad / 2),
+            text,
+            " ".repeat(pad - pad / 2)
+        ),
+        _ => format!("{}{}", text, " ".repeat(pad)),
+    }
+}
+
+/// Truncate to `max` display columns, ending with an ellipsis when cut
+fn truncate(value: &str, max: usize) -> String {
+    if value.width() <= max {
+        return value.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    let mut width = 0;
+    for c in value.chars() {
+        let w = c.width().unwrap_or(0);
+        if width + w > max - 1 {
+            break;
+        }
+        out.push(c);
+        width += w;
+    }
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_templates() {
+        let values: HashMap<String, String> = [
+            ("branch", "main"),
+            ("ahead", "2"),
+            ("behind", "0"),
+            ("dirty", "false"),
+            ("name", "feature/templates"),
+            ("wide", "日本語"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let cases = [
+            // Placeholders
+            ("{branch}", "main"),
+            ("{ branch }", "main"),
+            ("{missing}", ""),
+            ("[{branch}]", "[main]"),
+            // Padding and truncation
+            ("{branch:<6}|", "main  |"),
+            ("{branch:>6}", "  main"),
+            ("{branch:^7}", " main  "),
+            ("{name:.8}", "feature…"),
+            ("{name:>10.4}", "      fea…"),
+            ("{branch:.4}", "main"),
+            ("{branch:.0}", ""),
+            ("{wide:.5}", "日本…"),
+            ("{wide:>8}", "  日本語"),
+            // Conditionals
+            ("{?ahead:↑{ahead}}", "↑2"),
+            ("{?behind:↓{behind}}", ""),
+            ("{!behind:in sync}", "in sync"),
+            ("{!ahead:in sync}", ""),
+            ("{?dirty:*}", ""),
+            ("{?missing:x}", ""),
+            ("{?ahead:{?branch:{branch}}}", "main"),
+            ("{?ahead:{?behind:x}}!", "!"),
+            ("{?ahead:a{!behind:b}c}", "abc"),
+            // Escaped braces, also in conditional bodies
+            ("{{branch}}", "{branch}"),
+            ("{{{branch}}}", "{main}"),
+            ("{?ahead:{{x}}}", "{x}"),
+            ("{?ahead:x }} y}", "x } y"),
+            ("{?ahead:x}}}", "x}"),
+            ("{?behind:x }} y}z", "z"),
+            ("{?ahead:{?branch:x}}}}", "x}"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(render(template, &values), expected, "template {}", template);
+        }
+    }
+
+    #[test]
+    fn padding_is_clamped() {
+        let values = HashMap::from([("key".to_string(), "x".to_string())]);
+        assert_eq!(render("{key:>99999999}", &values).len(), MAX_PAD_WIDTH);
+        assert_eq!(render("{key:<256}", &values).len(), MAX_PAD_WIDTH);
+        assert_eq!(render("{key:<3}", &values), "x  ");
+    }
+}
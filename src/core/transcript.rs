@@ -0,0 +1,488 @@
+//! Fast access to Claude Code transcripts (JSONL session files).
+//!
+//! The latest usage is found by reading the transcript backwards from the
+//! end, so only the tail of a long session is parsed. Summaries point at an
+//! earlier message by `leafUuid`; those are resolved through a persisted
+//! per-project index of message offsets that is extended incrementally as
+//! session files grow. Session token totals, used to estimate cost, are
+//! accumulated incrementally the same way.
+
+use crate::config::{paths, NormalizedUsage, TranscriptEntry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Bytes read per step when scanning a file backwards
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Iterates over the lines of a file from last to first
+pub struct ReverseLines {
+    file: File,
+    /// Start of the region that has not been read yet
+    pos: u64,
+    /// Incomplete line at the start of the region read so far
+    partial: Vec<u8>,
+    /// Complete lines waiting to be returned, last line at the end
+    pending: Vec<Vec<u8>>,
+}
+
+impl ReverseLines {
+    pub fn open(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let pos = file.metadata().ok()?.len();
+        Some(Self {
+            file,
+            pos,
+            partial: Vec::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Read the previous chunk and split it into lines. Returns false at
+    /// the start of the file.
+    fn fill(&mut self) -> bool {
+        if self.pos == 0 {
+            if self.partial.is_empty() {
+                return false;
+            }
+            self.pending.push(std::mem::take(&mut self.partial));
+            return true;
+        }
+
+        let start = self.pos.saturating_sub(CHUNK_SIZE);
+        let mut chunk = vec![0u8; (self.pos - start) as usize];
+        if self.file.seek(SeekFrom::Start(start)).is_err()
+            || self.file.read_exact(&mut chunk).is_err()
+        {
+            self.pos = 0;
+            self.partial.clear();
+            return false;
+        }
+        self.pos = start;
+
+        chunk.extend_from_slice(&self.partial);
+        let mut pieces: Vec<Vec<u8>> = chunk.split(|&b| b == b'\n').map(<[u8]>::to_vec).collect();
+        // The first piece may continue in the previous chunk
+        self.partial = pieces.remove(0);
+        self.pending.extend(pieces);
+        true
+    }
+}
+
+impl Iterator for ReverseLines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if let Some(line) = self.pending.pop() {
+                return Some(String::from_utf8_lossy(&line).into_owned());
+            }
+            if !self.fill() {
+                return None;
+            }
+        }
+    }
+}
+
+/// Usage of the most recent assistant message for a session. Falls back to
+/// the newest session in the same project if the transcript doesn't exist.
+pub fn latest_usage(transcript_path: &Path) -> Option<NormalizedUsage> {
+    if let Some(usage) = last_usage_in_file(transcript_path) {
+        return Some(usage);
+    }
+
+    if !transcript_path.exists() {
+        return latest_usage_from_project_history(transcript_path);
+    }
+
+    None
+}
+
+/// Scan a transcript backwards for the last assistant usage entry. If the
+/// transcript ends with a summary, follow its `leafUuid` instead.
+fn last_usage_in_file(path: &Path) -> Option<NormalizedUsage> {
+    let mut lines = ReverseLines::open(path)?.filter(|line| !line.trim().is_empty());
+
+    let last_line = lines.next()?;
+    if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(last_line.trim()) {
+        if entry.r#type.as_deref() == Some("summary") {
+            if let Some(leaf_uuid) = &entry.leaf_uuid {
+                let project_dir = path.parent()?;
+                return find_usage_by_leaf_uuid(leaf_uuid, project_dir);
+            }
+        }
+        if let Some(usage) = assistant_usage(&entry) {
+            return Some(usage);
+        }
+    }
+
+    lines.find_map(|line| {
+        serde_json::from_str::<TranscriptEntry>(line.trim())
+            .ok()
+            .and_then(|entry| assistant_usage(&entry))
+    })
+}
+
+fn assistant_usage(entry: &TranscriptEntry) -> Option<NormalizedUsage> {
+    if entry.r#type.as_deref() != Some("assistant") {
+        return None;
+    }
+    let raw_usage = entry.message.as_ref()?.usage.as_ref()?;
+    Some(raw_usage.clone().normalize())
+}
+
+fn latest_usage_from_project_history(transcript_path: &Path) -> Option<NormalizedUsage> {
+    let project_dir = transcript_path.parent()?;
+    let mut session_files = list_session_files(project_dir);
+
+    // Most recently modified first
+    session_files.sort_by_key(|path| {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .unwrap_or(std::time::UNIX_EPOCH)
+    });
+    session_files.reverse();
+
+    session_files
+        .iter()
+        .find_map(|session_path| last_usage_in_file(session_path))
+}
+
+fn list_session_files(project_dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(project_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("jsonl"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Find the usage for a summary's `leafUuid` across the project's sessions.
+/// A user message resolves to its parent assistant message in the same file.
+pub fn find_usage_by_leaf_uuid(leaf_uuid: &str, project_dir: &Path) -> Option<NormalizedUsage> {
+    let session_files = list_session_files(project_dir);
+    let mut index = TranscriptIndex::load(project_dir);
+    index.refresh(&session_files);
+    index.save(project_dir);
+
+    session_files.iter().find_map(|path| {
+        let entry = index.entry_at(path, leaf_uuid)?;
+        match entry.r#type.as_deref() {
+            Some("assistant") => assistant_usage(&entry),
+            Some("user") => {
+                let parent = index.entry_at(path, entry.parent_uuid.as_deref()?)?;
+                assistant_usage(&parent)
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Tokens used by one model over a session
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TokenTotals {
+    pub input: u64,
+    pub output: u64,
+    pub cache_creation: u64,
+    pub cache_read: u64,
+}
+
+impl TokenTotals {
+    pub fn is_empty(&self) -> bool {
+        self.input + self.output + self.cache_creation + self.cache_read == 0
+    }
+
+    fn from_usage(usage: &NormalizedUsage) -> Self {
+        Self {
+            input: usage.input_tokens as u64,
+            output: usage.output_tokens as u64,
+            cache_creation: usage.cache_creation_input_tokens as u64,
+            cache_read: usage.cache_read_input_tokens as u64,
+        }
+    }
+
+    fn add(&mut self, other: &TokenTotals) {
+        self.input += other.input;
+        self.output += other.output;
+        self.cache_creation += other.cache_creation;
+        self.cache_read += other.cache_read;
+    }
+
+    fn subtract(&mut self, other: &TokenTotals) {
+        self.input = self.input.saturating_sub(other.input);
+        self.output = self.output.saturating_sub(other.output);
+        self.cache_creation = self.cache_creation.saturating_sub(other.cache_creation);
+        self.cache_read = self.cache_read.saturating_sub(other.cache_read);
+    }
+}
+
+/// Last message counted, so the repeated lines Claude Code writes for one
+/// message (one per content block) are counted once, with the latest usage
+#[derive(Debug, Serialize, Deserialize)]
+struct CountedMessage {
+    id: String,
+    model: String,
+    tokens: TokenTotals,
+}
+
+/// Running token totals for one transcript file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionTotals {
+    /// Byte offset up to which the file has been counted (always the end
+    /// of a complete line)
+    scanned_to: u64,
+    last_message: Option<CountedMessage>,
+    /// Model id → tokens. Messages without a model id are under "".
+    models: HashMap<String, TokenTotals>,
+}
+
+/// Token totals per model over every assistant message of a session. The
+/// model is taken from each message; "" collects messages without one.
+pub fn session_token_totals(transcript_path: &Path) -> Option<HashMap<String, TokenTotals>> {
+    let size = fs::metadata(transcript_path).ok()?.len();
+    let state_path = get_totals_path(transcript_path);
+
+    let mut totals: SessionTotals = state_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if size < totals.scanned_to {
+        // Rewritten: count from scratch
+        totals = SessionTotals::default();
+    }
+    if size == totals.scanned_to {
+        return Some(totals.models);
+    }
+
+    let mut file = File::open(transcript_path).ok()?;
+    file.seek(SeekFrom::Start(totals.scanned_to)).ok()?;
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n as u64,
+        };
+        // Leave a partially written last line for the next run
+        if line.last() != Some(&b'\n') {
+            break;
+        }
+        totals.scanned_to += read;
+
+        let Ok(entry) = serde_json::from_slice::<TranscriptEntry>(&line) else {
+            continue;
+        };
+        let Some(usage) = assistant_usage(&entry) else {
+            continue;
+        };
+        let message = entry.message.as_ref();
+        let model = message.and_then(|m| m.model.clone()).unwrap_or_default();
+        let tokens = TokenTotals::from_usage(&usage);
+
+        // A repeated message replaces its earlier count
+        let id = message.and_then(|m| m.id.clone());
+        if let (Some(id), Some(last)) = (&id, &totals.last_message) {
+            if *id == last.id {
+                if let Some(previous) = totals.models.get_mut(&last.model) {
+                    previous.subtract(&last.tokens);
+                }
+            }
+        }
+        totals.models.entry(model.clone()).or_default().add(&tokens);
+        totals.last_message = id.map(|id| CountedMessage { id, model, tokens });
+    }
+
+    if let Some(state_path) = state_path {
+        if let Some(parent) = state_path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&totals) {
+            let tmp_path = state_path.with_extension(format!("json.{}", std::process::id()));
+            if fs::write(&tmp_path, json).is_ok() && fs::rename(&tmp_path, &state_path).is_err() {
+                let _ = fs::remove_file(&tmp_path);
+            }
+        }
+    }
+
+    Some(totals.models)
+}
+
+/// One totals file per transcript, named after the session file
+fn get_totals_path(transcript_path: &Path) -> Option<PathBuf> {
+    let stem = transcript_path.file_stem()?.to_string_lossy();
+    Some(
+        paths::cache_dir()?
+            .join("session_totals")
+            .join(format!("{}.json", stem)),
+    )
+}
+
+/// Just enough of a transcript line to index it
+#[derive(Deserialize)]
+struct IndexProbe {
+    r#type: Option<String>,
+    uuid: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FileIndex {
+    /// Byte offset up to which the file has been indexed (always the end
+    /// of a complete line)
+    scanned_to: u64,
+    /// Message uuid → byte offset of its line
+    offsets: HashMap<String, u64>,
+}
+
+/// Offsets of user and assistant messages in every session file of one
+/// project directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TranscriptIndex {
+    files: HashMap<String, FileIndex>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl TranscriptIndex {
+    fn load(project_dir: &Path) -> Self {
+        Self::get_index_path(project_dir)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, project_dir: &Path) {
+        if !self.dirty {
+            return;
+        }
+
+        let Some(index_path) = Self::get_index_path(project_dir) else {
+            return;
+        };
+        if let Some(parent) = index_path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        // Temp file + rename so concurrent statusline processes never read
+        // a half-written index
+        if let Ok(json) = serde_json::to_string(self) {
+            let tmp_path = index_path.with_extension(format!("json.{}", std::process::id()));
+            if fs::write(&tmp_path, json).is_ok() && fs::rename(&tmp_path, &index_path).is_err() {
+                let _ = fs::remove_file(&tmp_path);
+            }
+        }
+    }
+
+    /// Index new lines appended to each session file and forget files that
+    /// no longer exist
+    fn refresh(&mut self, session_files: &[PathBuf]) {
+        let live: Vec<String> = session_files
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        let before = self.files.len();
+        self.files.retain(|key, _| live.contains(key));
+        if self.files.len() != before {
+            self.dirty = true;
+        }
+
+        for (path, key) in session_files.iter().zip(live) {
+            let file_index = self.files.entry(key).or_default();
+            if Self::extend(file_index, path) {
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Scan the part of `path` not yet indexed. Returns true if the index
+    /// changed. A file that shrank was rewritten and is indexed from scratch.
+    fn extend(file_index: &mut FileIndex, path: &Path) -> bool {
+        let Ok(size) = fs::metadata(path).map(|m| m.len()) else {
+            return false;
+        };
+        if size == file_index.scanned_to {
+            return false;
+        }
+        if size < file_index.scanned_to {
+            *file_index = FileIndex::default();
+        }
+
+        let Ok(mut file) = File::open(path) else {
+            return false;
+        };
+        if file.seek(SeekFrom::Start(file_index.scanned_to)).is_err() {
+            return false;
+        }
+
+        let mut reader = BufReader::new(file);
+        let mut offset = file_index.scanned_to;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n as u64,
+            };
+            // Leave a partially written last line for the next refresh
+            if line.last() != Some(&b'\n') {
+                break;
+            }
+
+            if let Ok(probe) = serde_json::from_slice::<IndexProbe>(&line) {
+                let indexed = matches!(probe.r#type.as_deref(), Some("assistant" | "user"));
+                if let (true, Some(uuid)) = (indexed, probe.uuid) {
+                    file_index.offsets.insert(uuid, offset);
+                }
+            }
+            offset += read;
+        }
+
+        let changed = offset != file_index.scanned_to;
+        file_index.scanned_to = offset;
+        changed
+    }
+
+    /// Read and parse the entry with `uuid` in `path`, if indexed
+    fn entry_at(&self, path: &Path, uuid: &str) -> Option<TranscriptEntry> {
+        let offset = *self
+            .files
+            .get(path.to_string_lossy().as_ref())?
+            .offsets
+            .get(uuid)?;
+
+        let mut file = File::open(path).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut line = String::new();
+        BufReader::new(file).read_line(&mut line).ok()?;
+
+        let entry: TranscriptEntry = serde_json::from_str(line.trim()).ok()?;
+        (entry.uuid.as_deref() == Some(uuid)).then_some(entry)
+    }
+
+    /// One index file per project directory, named after the directory
+    fn get_index_path(project_dir: &Path) -> Option<PathBuf> {
+        let cache_dir = paths::cache_dir()?;
+        let file_name: String = project_dir
+            .file_name()?
+            .to_string_lossy()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        Some(
+            cache_dir
+                .join("transcript_index")
+                .join(format!("{}.json", file_name)),
+        )
+    }
+}
@@ -1,5 +1,7 @@
-use micusubcodeline::cli::Cli;
+use micusubcodeline::cli::{Cli, Command, OutputFormat};
 use micusubcodeline::config::{Config, InputData};
+use micusubcodeline::core::backends::Markup;
+use micusubcodeline::core::history::{HistoryRecord, HistoryStore};
 use micusubcodeline::core::{collect_all_segments, StatusLineGenerator};
 use std::io::{self, IsTerminal};
 
@@ -45,14 +47,101 @@ fn detect_terminal_width() -> usize {
     80
 }
 
+/// Report the Claude Code OAuth token used by the Usage segment, refreshing
+/// it if it has expired
+fn print_oauth_status(config: &Config) {
+    use micusubcodeline::config::SegmentId;
+    use micusubcodeline::utils::credentials::{self, CredentialSource, TokenStatus};
+
+    println!("\n🔍 检测 Claude OAuth 凭据（Usage 段使用）...\n");
+    let describe = |source: &CredentialSource| match source {
+        CredentialSource::File(path) => path.display().to_string(),
+        CredentialSource::Keychain => "macOS 钥匙串".to_string(),
+    };
+
+    match credentials::token_status() {
+        TokenStatus::Missing => {
+            println!("❌ 未找到 OAuth 凭据");
+            println!("   请先在 Claude Code 中使用 /login 登录订阅账号");
+        }
+        TokenStatus::Valid {
+            source,
+            expires_in_secs,
+        } => {
+            println!("✅ 令牌有效（{}）", describe(&source));
+            if let Some(secs) = expires_in_secs {
+                println!("   剩余有效期: {}小时{}分", secs / 3600, secs % 3600 / 60);
+            }
+        }
+        TokenStatus::Expired {
+            source,
+            refreshable,
+        } => {
+            println!("⚠️  令牌已过期（{}）", describe(&source));
+            if !refreshable {
+                println!("   凭据中没有 refreshToken，请在 Claude Code 中重新登录");
+                return;
+            }
+
+            let token_url = credentials::token_url(
+                config
+                    .segments
+                    .iter()
+                    .find(|s| s.id == SegmentId::Usage)
+                    .and_then(|sc| sc.options.get("oauth_token_url"))
+                    .and_then(|v| v.as_str()),
+            );
+            println!("   正在通过 {} 刷新...", token_url);
+            match credentials::refresh_oauth_token(&token_url) {
+                Ok(_) => println!("✅ 刷新成功，新令牌已写回"),
+                Err(e) => println!("❌ 刷新失败: {}", e),
+            }
+        }
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse_args();
 
+    if let Some(config_dir) = &cli.config_dir {
+        micusubcodeline::config::paths::set_config_dir(config_dir.clone());
+    }
+
+    if let Some(Command::Report { by, format, days }) = cli.command {
+        use micusubcodeline::core::history::{build_report, format_report, HistoryStore};
+
+        let records = HistoryStore::load();
+        if records.is_empty() {
+            if let Some(path) = HistoryStore::history_path() {
+                eprintln!("No history recorded yet ({})", path.display());
+            }
+            return Ok(());
+        }
+
+        let since = days.map(|days| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            now.saturating_sub(days * 24 * 3600)
+        });
+        let rows = build_report(&records, by, since);
+        print!("{}", format_report(&rows, by, format));
+        return Ok(());
+    }
+
     // Handle configuration commands
     if cli.init {
         use micusubcodeline::config::InitResult;
         match Config::init()? {
             InitResult::Created(path) => println!("Created config at {}", path.display()),
+            InitResult::Imported { path, from } => {
+                println!(
+                    "Created config at {} from {}",
+                    path.display(),
+                    from.display()
+                )
+            }
             InitResult::AlreadyExists(path) => {
                 println!("Config already exists at {}", path.display())
             }
@@ -61,7 +150,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     if cli.print {
-        let mut config = Config::load().unwrap_or_else(|_| Config::default());
+        let mut config = match Config::load_with_report(false) {
+            Ok((config, report)) => {
+                // The upstream config is never rewritten, so there is
+                // nothing to save
+                if report.migrated() && Config::get_config_path().exists() {
+                    eprintln!(
+                        "micusubcodeline: config is version {}, upgraded to {} in memory; run --check to save the upgrade",
+                        report.from_version,
+                        micusubcodeline::config::migration::CONFIG_VERSION
+                    );
+                }
+                for warning in &report.warnings {
+                    eprintln!("micusubcodeline: {}", warning);
+                }
+                config
+            }
+            Err(_) => Config::default(),
+        };
 
         // Apply theme override if provided
         if let Some(theme) = cli.theme {
@@ -72,10 +178,95 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
+    if cli.schema {
+        let schema = micusubcodeline::config::schema::json_schema();
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        return Ok(());
+    }
+
     if cli.check {
-        let config = Config::load()?;
+        use micusubcodeline::config::schema;
+
+        // --check is the explicit request to save an upgraded config
+        let (config, report) = Config::load_with_report(true)?;
         config.check()?;
+
+        // Segment options of the global config and the project config that
+        // applies to the current directory
+        use micusubcodeline::config::ProjectConfig;
+        let project_path = std::env::current_dir()
+            .ok()
+            .and_then(|dir| ProjectConfig::discover(&dir));
+        let mut option_issues = Vec::new();
+        for path in std::iter::once(Config::get_config_path()).chain(project_path.clone()) {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                for issue in schema::check_options(&content) {
+                    let location = format!("{}:{}", path.display(), issue.line);
+                    option_issues.push((location, issue));
+                }
+            }
+        }
+        let errors = option_issues.iter().filter(|(_, i)| i.is_error).count();
+        if errors > 0 {
+            for (location, issue) in option_issues.iter().filter(|(_, i)| i.is_error) {
+                eprintln!("✗ {}: {}", location, issue.message);
+            }
+            return Err(format!("{} invalid option value(s)", errors).into());
+        }
+
         println!("✓ Configuration valid");
+        if let Some(backup) = &report.backup {
+            println!(
+                "  Upgraded from version {} (backup: {})",
+                report.from_version,
+                backup.display()
+            );
+        }
+        for warning in &report.warnings {
+            println!("  ⚠ {}", warning);
+        }
+        for (path, result) in micusubcodeline::config::ConfigLoader::upgrade_themes() {
+            match result {
+                Ok(report) => {
+                    if let Some(backup) = &report.backup {
+                        println!(
+                            "  Upgraded theme {} from version {} (backup: {})",
+                            path.display(),
+                            report.from_version,
+                            backup.display()
+                        );
+                    }
+                    for warning in &report.warnings {
+                        println!("  ⚠ {}: {}", path.display(), warning);
+                    }
+                }
+                Err(e) => println!("  ⚠ {}: {}", path.display(), e),
+            }
+        }
+        for (location, issue) in &option_issues {
+            println!("  ⚠ {}: {}", location, issue.message);
+        }
+        if let Some((dir, source)) = micusubcodeline::config::paths::resolve_config_dir() {
+            println!(
+                "  Config directory: {} ({})",
+                dir.display(),
+                source.describe()
+            );
+        }
+
+        if let Some(path) = project_path {
+            match ProjectConfig::load(&path) {
+                Ok(project) => {
+                    println!("  Project config: {}", path.display());
+                    for warning in project.warnings(&config) {
+                        println!("  ⚠ {}: {}", path.display(), warning);
+                    }
+                }
+                Err(e) => {
+                    return Err(format!("Invalid project config {}: {}", path.display(), e).into())
+                }
+            }
+        }
         return Ok(());
     }
 
@@ -83,24 +274,73 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         use micusubcodeline::utils::SubscriptionApi;
 
         println!("🔍 检测 API Key 状态...\n");
-        if let Some(api) = SubscriptionApi::load() {
+        let config = Config::load().unwrap_or_else(|_| Config::default());
+        if let Some(api) = SubscriptionApi::load_from_config(&config) {
             println!("✅ API Key 已检测到");
-            if let Some(sub) = api.get_subscription_info() {
+            println!("   候选端点（按顺序尝试）:");
+            for (i, endpoint) in api.endpoints().iter().enumerate() {
+                println!("   {}. {}", i + 1, endpoint);
+            }
+            if let Some((sub, endpoint)) = api.get_subscription_info_with_endpoint() {
+                println!("   响应端点: {}", endpoint);
                 println!("   分组: {}", sub.group_name);
                 println!("   今日消费: ${:.4}", sub.daily_used_usd);
             } else {
-                println!("   ⚠️  Key 已读取但无法获取订阅信息，请检查 Key 是否有效");
+                println!(
+                    "   ⚠️  Key 已读取但所有端点均无法获取订阅信息，请检查 Key 与端点是否有效"
+                );
             }
         } else {
+            use micusubcodeline::config::paths;
+
+            // 显示实际解析出的路径（受 CLAUDE_CONFIG_DIR / --config-dir 影响）
+            let display = |dir: Option<std::path::PathBuf>, file: &str| {
+                dir.map(|dir| dir.join(file).display().to_string())
+                    .unwrap_or_else(|| file.to_string())
+            };
             println!("❌ 未检测到 API Key");
             println!("\n   支持的读取位置（按优先级）：");
-            println!("   1. ~/.claude/settings.local.json → env.ANTHROPIC_API_KEY / ANTHROPIC_AUTH_TOKEN");
             println!(
-                "   2. ~/.claude/settings.json → env.ANTHROPIC_API_KEY / ANTHROPIC_AUTH_TOKEN"
+                "   1. {} → env.ANTHROPIC_API_KEY / ANTHROPIC_AUTH_TOKEN",
+                display(paths::claude_dir(), "settings.local.json")
+            );
+            println!(
+                "   2. {} → env.ANTHROPIC_API_KEY / ANTHROPIC_AUTH_TOKEN",
+                display(paths::claude_dir(), "settings.json")
             );
             println!("   3. 环境变量 ANTHROPIC_API_KEY / ANTHROPIC_AUTH_TOKEN");
-            println!("   4. ~/.claude/micusubcodeline/subscription_config.txt");
+            println!(
+                "   4. {}",
+                display(paths::config_dir(), "subscription_config.txt")
+            );
         }
+
+        print_oauth_status(&config);
+        return Ok(());
+    }
+
+    if cli.refresh_subscription {
+        use micusubcodeline::utils::SubscriptionApi;
+
+        // 优先使用状态栏进程传入的端点（可能来自项目配置），缺省时回退到全局配置
+        let api = if cli.subscription_endpoints.is_empty() {
+            let config = Config::load().unwrap_or_else(|_| Config::default());
+            SubscriptionApi::load_from_config(&config)
+        } else {
+            SubscriptionApi::load().map(|api| api.with_endpoint_list(cli.subscription_endpoints))
+        };
+        if let Some(api) = api {
+            api.refresh_cache();
+            api.release_refresh_lock();
+        }
+        return Ok(());
+    }
+
+    if cli.refresh_oauth {
+        use micusubcodeline::utils::credentials;
+
+        let token_url = credentials::token_url(cli.oauth_token_url.as_deref());
+        let _ = credentials::run_background_refresh(&token_url);
         return Ok(());
     }
 
@@ -155,12 +395,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    // Load configuration
-    let mut config = Config::load().unwrap_or_else(|_| Config::default());
+    // Load configuration; say why when falling back to the defaults
+    let mut config = Config::load().unwrap_or_else(|e| {
+        eprintln!(
+            "micusubcodeline: failed to load config, using defaults: {}",
+            e
+        );
+        Config::default()
+    });
 
     // Apply theme override if provided
-    if let Some(theme) = cli.theme {
-        config = micusubcodeline::ui::themes::ThemePresets::get_theme(&theme);
+    if let Some(theme) = &cli.theme {
+        config = micusubcodeline::ui::themes::ThemePresets::get_theme(theme);
     }
 
     // Check if stdin has data
@@ -204,26 +450,38 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Read Claude Code data from stdin
     let stdin = io::stdin();
-    let mut input: InputData = serde_json::from_reader(stdin.lock())?;
+    let input: InputData = serde_json::from_reader(stdin.lock())?;
 
-    // Try to fetch subscription data from API
-    if let Some(api) = micusubcodeline::utils::SubscriptionApi::load() {
-        if let Some(subscription) = api.get_subscription_info() {
-            input.subscription = Some(subscription);
-        }
+    // Layer the project's .micusubcodeline.toml over the global config; an
+    // explicit --theme wins over both
+    if cli.theme.is_none() {
+        config.apply_project(&input.workspace.current_dir);
     }
 
-    // Collect segment data
+    // Collect segment data (segments run concurrently within their time budgets)
     let segments_data = collect_all_segments(&config, &input);
 
-    // Render statusline with terminal-width-aware wrapping
+    // Keep a deduplicated record of this session's cost and usage
+    HistoryStore::record(&HistoryRecord::from_input(&input, &segments_data));
+
     let generator = StatusLineGenerator::new(config);
-    let terminal_width = detect_terminal_width();
-    let lines = generator.generate_wrapped(segments_data, terminal_width);
 
-    for line in lines {
-        println!("{}", line);
-    }
+    let output = match cli.format {
+        OutputFormat::Ansi => {
+            // Render statusline with terminal-width-aware wrapping
+            let terminal_width = detect_terminal_width();
+            generator
+                .generate_wrapped(segments_data, terminal_width)
+                .join("\n")
+        }
+        OutputFormat::Json => generator.generate_json(segments_data),
+        OutputFormat::Tmux => generator.generate_markup(Markup::Tmux, segments_data),
+        OutputFormat::Zsh => generator.generate_markup(Markup::Zsh, segments_data),
+        OutputFormat::Bash => generator.generate_markup(Markup::Bash, segments_data),
+        OutputFormat::I3bar => generator.generate_i3bar(segments_data),
+        OutputFormat::Waybar => generator.generate_waybar(segments_data),
+    };
+    println!("{}", output);
 
     Ok(())
 }
@@ -1,9 +1,11 @@
 use crate::config::{Config, SegmentId, StyleMode};
 use crate::ui::components::{
     color_picker::{ColorPickerComponent, NavDirection},
+    color_rule_editor::{ColorRuleEditorComponent, RuleColorTarget},
     help::HelpComponent,
     icon_selector::IconSelectorComponent,
     name_input::NameInputComponent,
+    options_editor::OptionsEditorComponent,
     preview::PreviewComponent,
     segment_list::{FieldSelection, Panel, SegmentListComponent},
     separator_editor::SeparatorEditorComponent,
@@ -31,8 +33,10 @@ pub struct App {
     selected_field: FieldSelection,
     should_quit: bool,
     color_picker: ColorPickerComponent,
+    color_rule_editor: ColorRuleEditorComponent,
     icon_selector: IconSelectorComponent,
     name_input: NameInputComponent,
+    options_editor: OptionsEditorComponent,
     preview: PreviewComponent,
     segment_list: SegmentListComponent,
     separator_editor: SeparatorEditorComponent,
@@ -51,8 +55,10 @@ impl App {
             selected_field: FieldSelection::Enabled,
             should_quit: false,
             color_picker: ColorPickerComponent::new(),
+            color_rule_editor: ColorRuleEditorComponent::new(),
             icon_selector: IconSelectorComponent::new(),
             name_input: NameInputComponent::new(),
+            options_editor: OptionsEditorComponent::new(),
             preview: PreviewComponent::new(),
             segment_list: SegmentListComponent::new(),
             separator_editor: SeparatorEditorComponent::new(),
@@ -138,7 +144,10 @@ impl App {
                     }
                 } else if app.color_picker.is_open {
                     match key.code {
-                        KeyCode::Esc => app.color_picker.close(),
+                        KeyCode::Esc => {
+                            app.color_rule_editor.color_target = None;
+                            app.color_picker.close();
+                        }
                         KeyCode::Up => app.color_picker.move_direction(NavDirection::Up),
                         KeyCode::Down => app.color_picker.move_direction(NavDirection::Down),
                         KeyCode::Left => app.color_picker.move_direction(NavDirection::Left),
@@ -147,7 +156,12 @@ impl App {
                         KeyCode::Char('r') => app.color_picker.switch_to_rgb(),
                         KeyCode::Enter => {
                             if let Some(color) = app.color_picker.get_selected_color() {
-                                app.apply_selected_color(color);
+                                if app.color_rule_editor.color_target.is_some() {
+                                    app.color_rule_editor.apply_color(color);
+                                    app.sync_color_rules();
+                                } else {
+                                    app.apply_selected_color(color);
+                                }
                             }
                             app.color_picker.close();
                         }
@@ -183,6 +197,103 @@ impl App {
                         }
                         _ => {}
                     }
+                } else if app.color_rule_editor.is_open {
+                    if app.color_rule_editor.editing {
+                        match key.code {
+                            KeyCode::Esc => app.color_rule_editor.cancel_edit(),
+                            KeyCode::Enter => {
+                                app.color_rule_editor.finish_edit();
+                                app.sync_color_rules();
+                            }
+                            KeyCode::Char(c) => app.color_rule_editor.input_char(c),
+                            KeyCode::Backspace => app.color_rule_editor.backspace(),
+                            _ => {}
+                        }
+                    } else {
+                        match key.code {
+                            KeyCode::Esc => {
+                                app.color_rule_editor.close();
+                                app.status_message = Some("Color rules updated".to_string());
+                            }
+                            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                                app.color_rule_editor.move_rule(-1);
+                                app.sync_color_rules();
+                            }
+                            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                                app.color_rule_editor.move_rule(1);
+                                app.sync_color_rules();
+                            }
+                            KeyCode::Up => app.color_rule_editor.move_selection(-1),
+                            KeyCode::Down => app.color_rule_editor.move_selection(1),
+                            KeyCode::Enter => app.color_rule_editor.start_edit(),
+                            KeyCode::Char('a') | KeyCode::Char('A') => {
+                                app.color_rule_editor.add_rule();
+                                app.sync_color_rules();
+                            }
+                            KeyCode::Char('d') | KeyCode::Char('D') => {
+                                app.color_rule_editor.delete_selected();
+                                app.sync_color_rules();
+                            }
+                            KeyCode::Char('b') | KeyCode::Char('B') => {
+                                app.color_rule_editor.toggle_bold();
+                                app.sync_color_rules();
+                            }
+                            KeyCode::Char('x') | KeyCode::Char('X') => {
+                                app.color_rule_editor.clear_colors();
+                                app.sync_color_rules();
+                            }
+                            KeyCode::Char('i') | KeyCode::Char('I') => {
+                                app.open_rule_color_picker(RuleColorTarget::Icon)
+                            }
+                            KeyCode::Char('t') | KeyCode::Char('T') => {
+                                app.open_rule_color_picker(RuleColorTarget::Text)
+                            }
+                            KeyCode::Char('g') | KeyCode::Char('G') => {
+                                app.open_rule_color_picker(RuleColorTarget::Background)
+                            }
+                            _ => {}
+                        }
+                    }
+                } else if app.options_editor.is_open {
+                    if app.options_editor.editing {
+                        match key.code {
+                            KeyCode::Esc => app.options_editor.cancel_edit(),
+                            KeyCode::Enter if app.options_editor.finish_edit() => {
+                                app.sync_options()
+                            }
+                            KeyCode::Char(c) => app.options_editor.input_char(c),
+                            KeyCode::Backspace => app.options_editor.backspace(),
+                            _ => {}
+                        }
+                    } else {
+                        let changed = match key.code {
+                            KeyCode::Esc => {
+                                app.options_editor.close();
+                                if app.options_editor.modified {
+                                    app.status_message = Some("Options updated".to_string());
+                                }
+                                false
+                            }
+                            KeyCode::Up => {
+                                app.options_editor.move_selection(-1);
+                                false
+                            }
+                            KeyCode::Down => {
+                                app.options_editor.move_selection(1);
+                                false
+                            }
+                            KeyCode::Left => app.options_editor.step(-1),
+                            KeyCode::Right => app.options_editor.step(1),
+                            KeyCode::Enter | KeyCode::Char(' ') => app.options_editor.activate(),
+                            KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Delete => {
+                                app.options_editor.reset_selected()
+                            }
+                            _ => false,
+                        };
+                        if changed {
+                            app.sync_options();
+                        }
+                    }
                 } else {
                     // Handle main app events
                     match key.code {
@@ -444,6 +555,18 @@ impl App {
         );
 
         // Render popups on top
+        if self.color_rule_editor.is_open {
+            let metadata = self
+                .config
+                .segments
+                .get(self.selected_segment)
+                .map(|segment| PreviewComponent::mock_segment_data(segment).metadata)
+                .unwrap_or_default();
+            self.color_rule_editor.render(f, f.area(), &metadata);
+        }
+        if self.options_editor.is_open {
+            self.options_editor.render(f, f.area());
+        }
         if self.color_picker.is_open {
             self.color_picker.render(f, f.area());
         }
@@ -468,7 +591,7 @@ impl App {
                 self.selected_segment = new_selection;
             }
             Panel::Settings => {
-                let field_count = 7; // Enabled, Icon, IconColor, TextColor, TextStyle, BackgroundColor, Options
+                let field_count = 8; // Enabled, Icon, IconColor, TextColor, TextStyle, BackgroundColor, ColorRules, Options
                 let current_field = match self.selected_field {
                     FieldSelection::Enabled => 0i32,
                     FieldSelection::Icon => 1,
@@ -476,7 +599,8 @@ impl App {
                     FieldSelection::TextColor => 3,
                     FieldSelection::BackgroundColor => 4,
                     FieldSelection::TextStyle => 5,
-                    FieldSelection::Options => 6,
+                    FieldSelection::ColorRules => 6,
+                    FieldSelection::Options => 7,
                 };
                 let new_field = (current_field + delta).clamp(0, field_count - 1) as usize;
                 self.selected_field = match new_field {
@@ -486,7 +610,8 @@ impl App {
                     3 => FieldSelection::TextColor,
                     4 => FieldSelection::BackgroundColor,
                     5 => FieldSelection::TextStyle,
-                    6 => FieldSelection::Options,
+                    6 => FieldSelection::ColorRules,
+                    7 => FieldSelection::Options,
                     _ => FieldSelection::Enabled,
                 };
             }
@@ -510,6 +635,7 @@ impl App {
                         SegmentId::OutputStyle => "Output Style",
                         SegmentId::Update => "Update",
                         SegmentId::Subscription => "Subscription",
+                        SegmentId::Custom => "Custom",
                     };
                     let is_enabled = segment.enabled;
                     self.status_message = Some(format!(
@@ -538,6 +664,7 @@ impl App {
                                 SegmentId::OutputStyle => "Output Style",
                                 SegmentId::Update => "Update",
                                 SegmentId::Subscription => "Subscription",
+                                SegmentId::Custom => "Custom",
                             };
                             let is_enabled = segment.enabled;
                             self.status_message = Some(format!(
@@ -567,11 +694,8 @@ impl App {
                             self.preview.update_preview(&self.config);
                         }
                     }
-                    FieldSelection::Options => {
-                        // TODO: Implement options editor
-                        self.status_message =
-                            Some("Options editor not implemented yet".to_string());
-                    }
+                    FieldSelection::ColorRules => self.open_color_rule_editor(),
+                    FieldSelection::Options => self.open_options_editor(),
                 }
             }
         }
@@ -594,6 +718,42 @@ impl App {
         }
     }
 
+    fn open_color_rule_editor(&mut self) {
+        if let Some(segment) = self.config.segments.get(self.selected_segment) {
+            self.color_rule_editor.open(segment.color_rules());
+        }
+    }
+
+    fn open_rule_color_picker(&mut self, target: RuleColorTarget) {
+        if !self.color_rule_editor.rules.is_empty() {
+            self.color_rule_editor.color_target = Some(target);
+            self.color_picker.open();
+        }
+    }
+
+    /// Write the rule editor's rules back to the selected segment
+    fn sync_color_rules(&mut self) {
+        if let Some(segment) = self.config.segments.get_mut(self.selected_segment) {
+            segment.set_color_rules(&self.color_rule_editor.rules);
+            self.preview.update_preview(&self.config);
+        }
+    }
+
+    fn open_options_editor(&mut self) {
+        if let Some(segment) = self.config.segments.get(self.selected_segment) {
+            self.options_editor.open(segment.id, &segment.options);
+        }
+    }
+
+    /// Write the options editor's values back to the selected segment
+    fn sync_options(&mut self) {
+        if let Some(segment) = self.config.segments.get_mut(self.selected_segment) {
+            segment.options = self.options_editor.options.clone();
+            self.options_editor.modified = true;
+            self.preview.update_preview(&self.config);
+        }
+    }
+
     fn open_icon_selector(&mut self) {
         if self.selected_panel == Panel::Settings && self.selected_field == FieldSelection::Icon {
             self.icon_selector.open(self.config.style.mode);
@@ -697,7 +697,7 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn ansi_to_ratatui_color(ansi: u8) -> Color {
+pub fn ansi_to_ratatui_color(ansi: u8) -> Color {
     match ansi {
         0 => Color::Black,
         1 => Color::Red,
@@ -719,7 +719,7 @@ fn ansi_to_ratatui_color(ansi: u8) -> Color {
     }
 }
 
-fn get_color_name(ansi: u8) -> &'static str {
+pub fn get_color_name(ansi: u8) -> &'static str {
     match ansi {
         0 => "Black",
         1 => "Red",
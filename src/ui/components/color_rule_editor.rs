@@ -0,0 +1,285 @@
+use super::color_picker::{ansi_to_ratatui_color, get_color_name};
+use crate::config::{AnsiColor, ColorRule};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::collections::HashMap;
+
+/// Which color of the selected rule the color picker is editing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleColorTarget {
+    Icon,
+    Text,
+    Background,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ColorRuleEditorComponent {
+    pub is_open: bool,
+    pub rules: Vec<ColorRule>,
+    pub selected: usize,
+    pub editing: bool,
+    pub input: String,
+    pub color_target: Option<RuleColorTarget>,
+}
+
+impl ColorRuleEditorComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&mut self, rules: Vec<ColorRule>) {
+        self.is_open = true;
+        self.rules = rules;
+        self.selected = 0;
+        self.editing = false;
+        self.input.clear();
+        self.color_target = None;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.editing = false;
+        self.input.clear();
+        self.color_target = None;
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        if self.rules.is_empty() {
+            return;
+        }
+        self.selected =
+            (self.selected as i32 + delta).clamp(0, self.rules.len() as i32 - 1) as usize;
+    }
+
+    /// Add a new rule after the selected one and start editing its condition
+    pub fn add_rule(&mut self) {
+        let rule = ColorRule {
+            when: "percentage >= 80".to_string(),
+            icon: None,
+            text: None,
+            background: Some(AnsiColor::Color16 { c16: 1 }),
+            text_bold: Some(true),
+        };
+        let index = if self.rules.is_empty() {
+            0
+        } else {
+            self.selected + 1
+        };
+        self.rules.insert(index, rule);
+        self.selected = index;
+        self.start_edit();
+    }
+
+    pub fn delete_selected(&mut self) {
+        if self.selected < self.rules.len() {
+            self.rules.remove(self.selected);
+            self.selected = self.selected.min(self.rules.len().saturating_sub(1));
+        }
+    }
+
+    /// Swap the selected rule with its neighbour; earlier rules win
+    pub fn move_rule(&mut self, delta: i32) {
+        let target = self.selected as i32 + delta;
+        if target >= 0 && (target as usize) < self.rules.len() {
+            self.rules.swap(self.selected, target as usize);
+            self.selected = target as usize;
+        }
+    }
+
+    pub fn start_edit(&mut self) {
+        if let Some(rule) = self.rules.get(self.selected) {
+            self.input = rule.when.clone();
+            self.editing = true;
+        }
+    }
+
+    pub fn finish_edit(&mut self) {
+        if let Some(rule) = self.rules.get_mut(self.selected) {
+            rule.when = self.input.trim().to_string();
+        }
+        self.editing = false;
+        self.input.clear();
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = false;
+        self.input.clear();
+    }
+
+    pub fn input_char(&mut self, c: char) {
+        if !c.is_control() {
+            self.input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    /// Cycle bold between inherit, on and off
+    pub fn toggle_bold(&mut self) {
+        if let Some(rule) = self.rules.get_mut(self.selected) {
+            rule.text_bold = match rule.text_bold {
+                None => Some(true),
+                Some(true) => Some(false),
+                Some(false) => None,
+            };
+        }
+    }
+
+    /// Reset the selected rule's colors so it inherits the segment's colors
+    pub fn clear_colors(&mut self) {
+        if let Some(rule) = self.rules.get_mut(self.selected) {
+            rule.icon = None;
+            rule.text = None;
+            rule.background = None;
+        }
+    }
+
+    pub fn apply_color(&mut self, color: AnsiColor) {
+        let target = self.color_target.take();
+        if let (Some(target), Some(rule)) = (target, self.rules.get_mut(self.selected)) {
+            match target {
+                RuleColorTarget::Icon => rule.icon = Some(color),
+                RuleColorTarget::Text => rule.text = Some(color),
+                RuleColorTarget::Background => rule.background = Some(color),
+            }
+        }
+    }
+
+    /// Render the rule list. `metadata` is the preview data of the segment,
+    /// used to show which rule currently applies.
+    pub fn render(&self, f: &mut Frame, area: Rect, metadata: &HashMap<String, String>) {
+        if !self.is_open {
+            return;
+        }
+
+        let popup_width = 70.min(area.width);
+        let popup_height = 20.min(area.height);
+        let popup_area = Rect {
+            x: (area.width.saturating_sub(popup_width)) / 2,
+            y: (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+
+        f.render_widget(Clear, popup_area);
+
+        let popup_block = Block::default().borders(Borders::ALL).title("Color Rules");
+        let inner = popup_block.inner(popup_area);
+        f.render_widget(popup_block, popup_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Condition input
+                Constraint::Min(5),    // Rule list
+                Constraint::Length(4), // Actions
+            ])
+            .split(inner);
+
+        let input_text = if self.editing {
+            format!("> {}_", self.input)
+        } else {
+            self.rules
+                .get(self.selected)
+                .map(|rule| rule.when.clone())
+                .unwrap_or_else(|| "No rules - press [A] to add one".to_string())
+        };
+        f.render_widget(
+            Paragraph::new(input_text)
+                .style(Style::default().fg(if self.editing {
+                    Color::Yellow
+                } else {
+                    Color::White
+                }))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Condition (e.g. percentage >= 80)"),
+                ),
+            chunks[0],
+        );
+
+        let active = self.rules.iter().position(|rule| rule.matches(metadata));
+        let lines: Vec<Line> = self
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                let marker = if i == self.selected { "▶ " } else { "  " };
+                let status = if Some(i) == active {
+                    Span::styled("[active] ", Style::default().fg(Color::Green))
+                } else if rule.parse_condition().is_none() {
+                    Span::styled("[invalid]", Style::default().fg(Color::Red))
+                } else {
+                    Span::raw("         ")
+                };
+                let mut spans = vec![
+                    Span::styled(marker, Style::default().fg(Color::Cyan)),
+                    status,
+                    Span::raw(format!(" {:<22}", rule.when)),
+                ];
+                spans.extend(color_spans("I", rule.icon.as_ref()));
+                spans.extend(color_spans("T", rule.text.as_ref()));
+                spans.extend(color_spans("B", rule.background.as_ref()));
+                let bold = match rule.text_bold {
+                    Some(true) => "bold",
+                    Some(false) => "no-bold",
+                    None => "",
+                };
+                spans.push(Span::styled(
+                    bold,
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                Line::from(spans)
+            })
+            .collect();
+
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Rules (first match wins)"),
+            ),
+            chunks[1],
+        );
+
+        let actions = if self.editing {
+            "[Enter] Confirm  [Esc] Cancel"
+        } else {
+            "[A] Add  [D] Delete  [Enter] Edit  [Shift+↑↓] Reorder  [B] Bold\n\
+             [I/T/G] Icon/Text/Background Color  [X] Clear Colors  [Esc] Done"
+        };
+        f.render_widget(
+            Paragraph::new(actions).block(Block::default().borders(Borders::ALL)),
+            chunks[2],
+        );
+    }
+}
+
+/// A short label plus a color swatch, or nothing if the rule keeps the
+/// segment's own color
+fn color_spans(label: &str, color: Option<&AnsiColor>) -> Vec<Span<'static>> {
+    let Some(color) = color else {
+        return Vec::new();
+    };
+    let (ratatui_color, name) = match color {
+        AnsiColor::Color16 { c16 } => (ansi_to_ratatui_color(*c16), get_color_name(*c16).into()),
+        AnsiColor::Color256 { c256 } => (Color::Indexed(*c256), format!("256:{}", c256)),
+        AnsiColor::Rgb { r, g, b } => (
+            Color::Rgb(*r, *g, *b),
+            format!("#{:02x}{:02x}{:02x}", r, g, b),
+        ),
+    };
+    vec![
+        Span::raw(format!("{}:", label)),
+        Span::styled("██", Style::default().fg(ratatui_color)),
+        Span::raw(format!("{} ", name)),
+    ]
+}
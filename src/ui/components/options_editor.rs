@@ -0,0 +1,397 @@
+use crate::config::schema::{self, OptionKind, OptionSpec};
+use crate::config::SegmentId;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Popup listing a segment's known options with a widget per type:
+/// booleans toggle, enums cycle, everything else is edited as text
+#[derive(Debug, Clone, Default)]
+pub struct OptionsEditorComponent {
+    pub is_open: bool,
+    /// Working copy of the segment's options, written back after each change
+    pub options: HashMap<String, Value>,
+    pub specs: Vec<OptionSpec>,
+    pub selected: usize,
+    pub editing: bool,
+    pub input: String,
+    pub error: Option<String>,
+    /// Whether any change was written back since the editor was opened
+    pub modified: bool,
+}
+
+impl OptionsEditorComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Color rules have their own editor and are left out here
+    pub fn open(&mut self, id: SegmentId, options: &HashMap<String, Value>) {
+        self.is_open = true;
+        self.options = options.clone();
+        self.specs = schema::segment_options(id)
+            .into_iter()
+            .filter(|spec| spec.kind != OptionKind::ColorRules)
+            .collect();
+        self.selected = 0;
+        self.editing = false;
+        self.input.clear();
+        self.error = None;
+        self.modified = false;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.editing = false;
+        self.input.clear();
+        self.error = None;
+    }
+
+    pub fn move_selection(&mut self, delta: i32) {
+        if self.specs.is_empty() {
+            return;
+        }
+        self.selected =
+            (self.selected as i32 + delta).clamp(0, self.specs.len() as i32 - 1) as usize;
+        self.error = None;
+    }
+
+    fn selected_spec(&self) -> Option<OptionSpec> {
+        self.specs.get(self.selected).copied()
+    }
+
+    /// Current value of an option, falling back to its default
+    fn effective_value(&self, spec: &OptionSpec) -> Option<Value> {
+        self.options
+            .get(spec.name)
+            .cloned()
+            .or_else(|| spec.default.to_value())
+    }
+
+    /// Enter on an option: toggle a boolean, cycle an enum, otherwise start
+    /// editing. Returns true if the options changed.
+    pub fn activate(&mut self) -> bool {
+        let Some(spec) = self.selected_spec() else {
+            return false;
+        };
+        match spec.kind {
+            OptionKind::Bool | OptionKind::Enum(_) => self.step(1),
+            _ => {
+                self.input = self
+                    .effective_value(&spec)
+                    .map(|value| value_to_input(&value))
+                    .unwrap_or_default();
+                self.editing = true;
+                self.error = None;
+                false
+            }
+        }
+    }
+
+    /// Left/Right on an option: toggle a boolean, cycle an enum or nudge an
+    /// integer within its range. Returns true if the options changed.
+    pub fn step(&mut self, delta: i64) -> bool {
+        let Some(spec) = self.selected_spec() else {
+            return false;
+        };
+        let current = self.effective_value(&spec);
+        let value = match spec.kind {
+            OptionKind::Bool => Value::Bool(!current.and_then(|v| v.as_bool()).unwrap_or(false)),
+            OptionKind::Enum(values) => {
+                let index = current
+                    .as_ref()
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| values.iter().position(|v| *v == s));
+                let next = match index {
+                    Some(i) => (i as i64 + delta).rem_euclid(values.len() as i64) as usize,
+                    None => 0,
+                };
+                Value::from(values[next])
+            }
+            OptionKind::Integer { min, max } => {
+                let n = current.and_then(|v| v.as_u64()).unwrap_or(min);
+                Value::from(n.saturating_add_signed(delta).clamp(min, max))
+            }
+            _ => return false,
+        };
+        self.options.insert(spec.name.to_string(), value);
+        self.error = None;
+        true
+    }
+
+    /// Remove the selected option so its default applies
+    pub fn reset_selected(&mut self) -> bool {
+        let Some(spec) = self.selected_spec() else {
+            return false;
+        };
+        self.error = None;
+        self.options.remove(spec.name).is_some()
+    }
+
+    /// Parse and validate the text input. On error the editor stays in edit
+    /// mode with the message shown; an empty input resets to the default.
+    pub fn finish_edit(&mut self) -> bool {
+        let Some(spec) = self.selected_spec() else {
+            return false;
+        };
+        let input = self.input.trim();
+        if input.is_empty() {
+            self.cancel_edit();
+            return self.options.remove(spec.name).is_some();
+        }
+        let value = match parse_input(&spec, input) {
+            Ok(value) => value,
+            Err(e) => {
+                self.error = Some(e);
+                return false;
+            }
+        };
+        if let Err(e) = spec.validate(&value) {
+            self.error = Some(e);
+            return false;
+        }
+        self.options.insert(spec.name.to_string(), value);
+        self.cancel_edit();
+        true
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = false;
+        self.input.clear();
+        self.error = None;
+    }
+
+    pub fn input_char(&mut self, c: char) {
+        if !c.is_control() {
+            self.input.push(c);
+            self.error = None;
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+        self.error = None;
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        if !self.is_open {
+            return;
+        }
+
+        let popup_width = 76.min(area.width);
+        let popup_height = (self.specs.len() as u16 + 12)
+            .clamp(14, 26)
+            .min(area.height);
+        let popup_area = Rect {
+            x: (area.width.saturating_sub(popup_width)) / 2,
+            y: (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+
+        f.render_widget(Clear, popup_area);
+
+        let popup_block = Block::default().borders(Borders::ALL).title("Options");
+        let inner = popup_block.inner(popup_area);
+        f.render_widget(popup_block, popup_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),    // Option list
+                Constraint::Length(4), // Description / input
+                Constraint::Length(3), // Actions
+            ])
+            .split(inner);
+
+        let name_width = self
+            .specs
+            .iter()
+            .map(|spec| spec.name.len())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = if self.specs.is_empty() {
+            vec![Line::from(
+                "This segment has no options besides color rules",
+            )]
+        } else {
+            self.specs
+                .iter()
+                .enumerate()
+                .map(|(i, spec)| {
+                    let selected = i == self.selected;
+                    let marker = if selected { "▶ " } else { "  " };
+                    let name_style = if selected {
+                        Style::default()
+                            .fg(Color::Cyan)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+                    let mut spans = vec![
+                        Span::styled(marker, Style::default().fg(Color::Cyan)),
+                        Span::styled(
+                            format!("{:<width$}  ", spec.name, width = name_width),
+                            name_style,
+                        ),
+                    ];
+                    spans.extend(self.value_spans(spec));
+                    Line::from(spans)
+                })
+                .collect()
+        };
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL)),
+            chunks[0],
+        );
+
+        let detail = if self.editing {
+            let mut lines = vec![Line::from(Span::styled(
+                format!("> {}_", self.input),
+                Style::default().fg(Color::Yellow),
+            ))];
+            if let Some(error) = &self.error {
+                lines.push(Line::from(Span::styled(
+                    error.clone(),
+                    Style::default().fg(Color::Red),
+                )));
+            } else if let Some(spec) = self.selected_spec() {
+                lines.push(Line::from(Span::styled(
+                    input_hint(&spec),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            lines
+        } else if let Some(spec) = self.selected_spec() {
+            let default = spec
+                .default
+                .to_value()
+                .map(|v| value_to_input(&v))
+                .unwrap_or_else(|| "unset".to_string());
+            vec![
+                Line::from(spec.description),
+                Line::from(Span::styled(
+                    format!("{}, default: {}", spec.kind.describe(), default),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ]
+        } else {
+            Vec::new()
+        };
+        let title = self
+            .selected_spec()
+            .map(|spec| spec.name)
+            .unwrap_or_default();
+        f.render_widget(
+            Paragraph::new(detail)
+                .wrap(ratatui::widgets::Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).title(title)),
+            chunks[1],
+        );
+
+        let actions = if self.editing {
+            "[Enter] Confirm  [Esc] Cancel  (empty resets to default)"
+        } else {
+            "[Enter] Toggle/Edit  [←→] Change  [D] Reset to Default  [Esc] Done"
+        };
+        f.render_widget(
+            Paragraph::new(actions).block(Block::default().borders(Borders::ALL)),
+            chunks[2],
+        );
+    }
+
+    /// The value widget of an option; defaults are shown dimmed
+    fn value_spans(&self, spec: &OptionSpec) -> Vec<Span<'static>> {
+        let set = self.options.get(spec.name);
+        let value = set.cloned().or_else(|| spec.default.to_value());
+        let style = match set {
+            Some(value) if spec.validate(value).is_err() => Style::default().fg(Color::Red),
+            Some(_) => Style::default().fg(Color::Green),
+            None => Style::default().fg(Color::DarkGray),
+        };
+        let text = match (spec.kind, &value) {
+            (OptionKind::Bool, value) => {
+                let on = value.as_ref().and_then(|v| v.as_bool()).unwrap_or(false);
+                if on { "[x]" } else { "[ ]" }.to_string()
+            }
+            (OptionKind::Enum(_), Some(value)) => format!("◀ {} ▶", value_to_input(value)),
+            (_, Some(value)) => value_to_input(value),
+            (_, None) => "unset".to_string(),
+        };
+        let mut spans = vec![Span::styled(text, style)];
+        if set.is_none() {
+            spans.push(Span::styled(
+                " (default)",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        spans
+    }
+}
+
+/// Text form of a value: lists as `a, b`, tables as `key = value, ...`
+fn value_to_input(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(value_to_input)
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Object(table) => table
+            .iter()
+            .map(|(key, value)| format!("{} = {}", key, value_to_input(value)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// Inverse of `value_to_input` for the option's kind
+fn parse_input(spec: &OptionSpec, input: &str) -> Result<Value, String> {
+    let split = |s: &str| -> Vec<String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    match spec.kind {
+        OptionKind::Integer { .. } => input
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| format!("'{}' is not a whole number", input)),
+        OptionKind::StringList(_) => Ok(Value::from(split(input))),
+        OptionKind::StringMap(_) => split(input)
+            .iter()
+            .map(|entry| match entry.split_once('=') {
+                Some((key, value)) => Ok((
+                    key.trim().to_string(),
+                    Value::from(value.trim().to_string()),
+                )),
+                None => Err(format!("expected key = value, found '{}'", entry)),
+            })
+            .collect::<Result<serde_json::Map<_, _>, _>>()
+            .map(Value::Object),
+        _ => Ok(Value::from(input)),
+    }
+}
+
+fn input_hint(spec: &OptionSpec) -> String {
+    match spec.kind {
+        OptionKind::StringList(_) => format!("{}, separated by commas", spec.kind.describe()),
+        OptionKind::StringMap(_) => {
+            format!(
+                "{}, as key = value separated by commas",
+                spec.kind.describe()
+            )
+        }
+        kind => kind.describe(),
+    }
+}
@@ -1,5 +1,6 @@
 use crate::config::{Config, SegmentId};
 use crate::core::segments::SegmentData;
+use crate::core::template::apply_segment_format;
 use crate::core::StatusLineGenerator;
 use ratatui::{
     layout::Rect,
@@ -87,119 +88,236 @@ impl PreviewComponent {
         &self,
         config: &Config,
     ) -> Vec<(crate::config::SegmentConfig, SegmentData)> {
-        let mut segments_data = Vec::new();
-
-        for segment_config in &config.segments {
-            if !segment_config.enabled {
-                continue;
-            }
+        config
+            .segments
+            .iter()
+            .filter(|segment_config| segment_config.enabled)
+            .map(|segment_config| {
+                (
+                    segment_config.clone(),
+                    Self::mock_segment_data(segment_config),
+                )
+            })
+            .collect()
+    }
 
-            let mock_data = match segment_config.id {
-                SegmentId::Model => SegmentData {
-                    primary: "Sonnet 4".to_string(),
-                    secondary: "".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert("model".to_string(), "claude-4-sonnet-20250512".to_string());
-                        map
-                    },
+    /// Mock data for a single segment, with its format options applied.
+    /// Also used by editors that evaluate options against preview data.
+    pub fn mock_segment_data(segment_config: &crate::config::SegmentConfig) -> SegmentData {
+        let mut mock_data = match segment_config.id {
+            SegmentId::Model => SegmentData {
+                primary: "Sonnet 4".to_string(),
+                secondary: "".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("model".to_string(), "claude-4-sonnet-20250512".to_string());
+                    map.insert("name".to_string(), "Sonnet 4".to_string());
+                    map
                 },
-                SegmentId::Directory => SegmentData {
-                    primary: "MicuSubCodeLine".to_string(),
-                    secondary: "".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert("current_dir".to_string(), "~/MicuSubCodeLine".to_string());
-                        map
-                    },
+            },
+            SegmentId::Directory => SegmentData {
+                primary: "MicuSubCodeLine".to_string(),
+                secondary: "".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("current_dir".to_string(), "~/MicuSubCodeLine".to_string());
+                    map.insert("dir_name".to_string(), "MicuSubCodeLine".to_string());
+                    map.insert("display_path".to_string(), "MicuSubCodeLine".to_string());
+                    map
                 },
-                SegmentId::Git => SegmentData {
-                    primary: "master".to_string(),
-                    secondary: "✓".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert("branch".to_string(), "master".to_string());
-                        map.insert("status".to_string(), "Clean".to_string());
-                        map.insert("ahead".to_string(), "0".to_string());
-                        map.insert("behind".to_string(), "0".to_string());
-                        map
-                    },
+            },
+            SegmentId::Git => SegmentData {
+                primary: "master".to_string(),
+                secondary: "✓".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("branch".to_string(), "master".to_string());
+                    map.insert("detached".to_string(), "false".to_string());
+                    map.insert("repo".to_string(), "MicuSubCodeLine".to_string());
+                    map.insert("status".to_string(), "Clean".to_string());
+                    map.insert("ahead".to_string(), "0".to_string());
+                    map.insert("behind".to_string(), "0".to_string());
+                    map.insert("status_icon".to_string(), "✓".to_string());
+                    for key in [
+                        "staged",
+                        "modified",
+                        "deleted",
+                        "renamed",
+                        "untracked",
+                        "conflicted",
+                        "stash",
+                    ] {
+                        map.insert(key.to_string(), "0".to_string());
+                    }
+                    map
                 },
-                SegmentId::ContextWindow => SegmentData {
-                    primary: "78.2%".to_string(),
-                    secondary: "· 156.4k".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert("total_tokens".to_string(), "156400".to_string());
-                        map.insert("percentage".to_string(), "78.2".to_string());
-                        map.insert("session_tokens".to_string(), "48200".to_string());
-                        map
-                    },
+            },
+            SegmentId::ContextWindow => SegmentData {
+                primary: "78.2%".to_string(),
+                secondary: "· 156.4k".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("total_tokens".to_string(), "156400".to_string());
+                    map.insert("percentage".to_string(), "78.2".to_string());
+                    map.insert("session_tokens".to_string(), "48200".to_string());
+                    map.insert("pct".to_string(), "78.2%".to_string());
+                    map.insert("tokens_short".to_string(), "156.4k".to_string());
+                    map.insert("headroom".to_string(), "0".to_string());
+                    map.insert("headroom_short".to_string(), "0".to_string());
+                    map
                 },
-                SegmentId::Usage => SegmentData {
-                    primary: "24%".to_string(),
-                    secondary: "· 10-7-2".to_string(),
-                    metadata: HashMap::new(),
+            },
+            SegmentId::Usage => SegmentData {
+                primary: "5h 24% · 7d 61%".to_string(),
+                secondary: "· 5h 2h13m / 7d 3d4h".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("five_hour_percent".to_string(), "24".to_string());
+                    map.insert("five_hour_utilization".to_string(), "24".to_string());
+                    map.insert("five_hour_reset".to_string(), "2h13m".to_string());
+                    map.insert("seven_day_percent".to_string(), "61".to_string());
+                    map.insert("seven_day_utilization".to_string(), "61".to_string());
+                    map.insert("seven_day_reset".to_string(), "3d4h".to_string());
+                    map.insert("reset_time".to_string(), "3d4h".to_string());
+                    map
                 },
-                SegmentId::Cost => SegmentData {
-                    primary: "$0.02".to_string(),
-                    secondary: "".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert("cost".to_string(), "0.01234".to_string());
-                        map
-                    },
+            },
+            SegmentId::Cost => SegmentData {
+                primary: "$0.02".to_string(),
+                secondary: "".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("cost".to_string(), "0.01234".to_string());
+                    map.insert("cost_display".to_string(), "$0.02".to_string());
+                    map.insert("estimated".to_string(), "false".to_string());
+                    map
                 },
-                SegmentId::Session => SegmentData {
-                    primary: "3m45s".to_string(),
-                    secondary: "+156 -23".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert("duration_ms".to_string(), "225000".to_string());
-                        map.insert("lines_added".to_string(), "156".to_string());
-                        map.insert("lines_removed".to_string(), "23".to_string());
-                        map
-                    },
+            },
+            SegmentId::Session => SegmentData {
+                primary: "3m45s".to_string(),
+                secondary: "+156 -23".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("duration_ms".to_string(), "225000".to_string());
+                    map.insert("lines_added".to_string(), "156".to_string());
+                    map.insert("lines_removed".to_string(), "23".to_string());
+                    map.insert("duration".to_string(), "3m45s".to_string());
+                    map.insert("line_changes".to_string(), "+156 -23".to_string());
+                    map
                 },
-                SegmentId::OutputStyle => SegmentData {
-                    primary: "default".to_string(),
-                    secondary: "".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert("style_name".to_string(), "default".to_string());
-                        map
-                    },
+            },
+            SegmentId::OutputStyle => SegmentData {
+                primary: "default".to_string(),
+                secondary: "".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("style_name".to_string(), "default".to_string());
+                    map
                 },
-                SegmentId::Update => SegmentData {
-                    primary: format!("v{}", env!("CARGO_PKG_VERSION")),
-                    secondary: "".to_string(),
-                    metadata: {
-                        let mut map = HashMap::new();
-                        map.insert(
-                            "current_version".to_string(),
-                            env!("CARGO_PKG_VERSION").to_string(),
-                        );
-                        map.insert("update_available".to_string(), "false".to_string());
-                        map
-                    },
+            },
+            SegmentId::Update => SegmentData {
+                primary: format!("v{}", env!("CARGO_PKG_VERSION")),
+                secondary: "".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert(
+                        "current_version".to_string(),
+                        env!("CARGO_PKG_VERSION").to_string(),
+                    );
+                    map.insert("update_available".to_string(), "false".to_string());
+                    map
+                },
+            },
+            SegmentId::Subscription => SegmentData {
+                primary: "MICU-Ultra | 今日:$2.48 本周:$68.80/$140.00".to_string(),
+                secondary: "刷新:9小时32分".to_string(),
+                metadata: {
+                    let mut map = HashMap::new();
+                    map.insert("group_name".to_string(), "MICU-Ultra".to_string());
+                    map.insert("daily_cost".to_string(), "2.48".to_string());
+                    map.insert("weekly_cost".to_string(), "68.80".to_string());
+                    map.insert("weekly_limit".to_string(), "140.00".to_string());
+                    map.insert("daily_usd".to_string(), "$2.48".to_string());
+                    map.insert("weekly_usd".to_string(), "$68.80".to_string());
+                    map.insert("weekly_limit_usd".to_string(), "$140.00".to_string());
+                    map.insert("weekly_percent".to_string(), "49.1".to_string());
+                    map.insert("resets_in".to_string(), "9小时32分".to_string());
+                    map
                 },
-                SegmentId::Subscription => SegmentData {
-                    primary: "MICU-Ultra | 今日:$2.48 本周:$68.80/$140.00".to_string(),
-                    secondary: "刷新:9小时32分".to_string(),
+            },
+            SegmentId::Custom => {
+                let name = segment_config.custom_name().unwrap_or("custom").to_string();
+                SegmentData {
+                    primary: name.clone(),
+                    secondary: "".to_string(),
                     metadata: {
                         let mut map = HashMap::new();
-                        map.insert("group_name".to_string(), "MICU-Ultra".to_string());
-                        map.insert("daily_cost".to_string(), "2.48".to_string());
-                        map.insert("weekly_cost".to_string(), "68.80".to_string());
-                        map.insert("weekly_limit".to_string(), "140.00".to_string());
+                        map.insert("name".to_string(), name);
                         map
                     },
-                },
-            };
+                }
+            }
+        };
 
-            segments_data.push((segment_config.clone(), mock_data));
-        }
+        Self::apply_mock_options(segment_config, &mut mock_data);
+        apply_segment_format(segment_config, &mut mock_data);
+        mock_data
+    }
 
-        segments_data
+    /// Reflect the options that change a segment's default text, so edits
+    /// in the options editor show up in the preview
+    fn apply_mock_options(segment_config: &crate::config::SegmentConfig, data: &mut SegmentData) {
+        let options = &segment_config.options;
+        let flag = |key: &str| options.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        match segment_config.id {
+            SegmentId::Git => {
+                if flag("show_sha") {
+                    data.metadata
+                        .insert("sha".to_string(), "a1b2c3d".to_string());
+                    data.secondary.push_str(" a1b2c3d");
+                }
+                if flag("diff_stats") {
+                    data.metadata
+                        .insert("diff".to_string(), "+12 -3".to_string());
+                    data.secondary.push_str(" +12 -3");
+                }
+            }
+            SegmentId::ContextWindow => {
+                let mut parts = Vec::new();
+                if flag("breakdown") {
+                    parts.push("R 14.3k W 28.7k I 489 O 220");
+                }
+                if flag("headroom") {
+                    parts.push("111.3k to compact (~3 turns)");
+                }
+                if !parts.is_empty() {
+                    data.secondary = format!("· {}", parts.join(" · "));
+                }
+            }
+            SegmentId::Usage => {
+                use crate::core::segments::{ResetFormat, UsageSegment, UsageWindow};
+
+                let reset_format =
+                    ResetFormat::from_option(options.get("reset_format").and_then(|v| v.as_str()));
+                let now = chrono::Utc::now();
+                for (key, minutes) in [("five_hour", 2 * 60 + 13), ("seven_day", (3 * 24 + 4) * 60)]
+                {
+                    let reset = reset_format.format(now + chrono::Duration::minutes(minutes), now);
+                    data.metadata.insert(format!("{}_reset", key), reset);
+                }
+                if let Some(reset) = data.metadata.get("seven_day_reset").cloned() {
+                    data.metadata.insert("reset_time".to_string(), reset);
+                }
+
+                let windows = UsageWindow::list_from_option(options.get("windows"));
+                UsageSegment::new()
+                    .with_windows(windows)
+                    .insert_summary(&mut data.metadata);
+                let (primary, secondary) = UsageSegment::default_text(&data.metadata);
+                data.primary = primary;
+                data.secondary = secondary;
+            }
+            _ => {}
+        }
     }
 }
@@ -21,6 +21,7 @@ pub enum FieldSelection {
     TextColor,
     BackgroundColor,
     TextStyle,
+    ColorRules,
     Options,
 }
 
@@ -58,6 +59,13 @@ impl SegmentListComponent {
                     SegmentId::OutputStyle => "Output Style",
                     SegmentId::Update => "Update",
                     SegmentId::Subscription => "Subscription",
+                    SegmentId::Custom => "Custom",
+                };
+
+                // Custom segments share an ID, show their name to tell them apart
+                let segment_name = match segment.custom_name() {
+                    Some(name) => format!("{}: {}", segment_name, name),
+                    None => segment_name.to_string(),
                 };
 
                 if is_selected {
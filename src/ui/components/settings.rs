@@ -37,6 +37,7 @@ impl SettingsComponent {
                 SegmentId::OutputStyle => "Output Style",
                 SegmentId::Update => "Update",
                 SegmentId::Subscription => "Subscription",
+                SegmentId::Custom => "Custom",
             };
             let current_icon = match config.style.mode {
                 StyleMode::Plain => &segment.icon.plain,
@@ -267,6 +268,13 @@ impl SettingsComponent {
                         }
                     ))],
                 ),
+                create_field_line(
+                    FieldSelection::ColorRules,
+                    vec![Span::raw(format!(
+                        "├─ Color Rules: {} rules",
+                        segment.color_rules().len()
+                    ))],
+                ),
                 create_field_line(
                     FieldSelection::Options,
                     vec![Span::raw(format!(
@@ -84,10 +84,8 @@ impl MainMenu {
                     KeyCode::Esc | KeyCode::Char('q') => {
                         self.should_quit = true;
                     }
-                    KeyCode::Up => {
-                        if self.selected_item > 0 {
-                            self.selected_item -= 1;
-                        }
+                    KeyCode::Up if self.selected_item > 0 => {
+                        self.selected_item -= 1;
                     }
                     KeyCode::Down => {
                         let menu_items = self.get_menu_items();
@@ -134,6 +132,16 @@ impl MainMenu {
                             is_error: false,
                         });
                     }
+                    Ok(InitResult::Imported { path, from }) => {
+                        self.status_message = Some(StatusMessage {
+                            message: format!(
+                                "✓ Created config at {} from {}",
+                                path.display(),
+                                from.display()
+                            ),
+                            is_error: false,
+                        });
+                    }
                     Ok(InitResult::AlreadyExists(path)) => {
                         self.status_message = Some(StatusMessage {
                             message: format!("Config already exists at {}", path.display()),
@@ -150,8 +158,9 @@ impl MainMenu {
                 None // Stay in menu
             }
             2 => {
-                // Check config and show result in footer
-                match crate::config::Config::load() {
+                // Check config and show result in footer (without the stderr
+                // notes of `Config::load`, which would garble the screen)
+                match crate::config::Config::load_with_report(false).map(|(config, _)| config) {
                     Ok(config) => match config.check() {
                         Ok(_) => {
                             self.status_message = Some(StatusMessage {
@@ -1,5 +1,6 @@
 // Theme presets for TUI configuration
 
+use crate::config::migration::{self, CONFIG_VERSION};
 use crate::config::{Config, StyleConfig, StyleMode};
 
 // Import all theme modules
@@ -17,7 +18,12 @@ impl ThemePresets {
             return config;
         }
 
-        // Fallback to built-in themes
+        Self::get_builtin(theme_name)
+    }
+
+    /// A built-in theme, ignoring theme files; unknown names give the
+    /// default theme
+    pub fn get_builtin(theme_name: &str) -> Config {
         match theme_name {
             "cometix" => Self::get_cometix(),
             "default" => Self::get_default(),
@@ -41,8 +47,8 @@ impl ThemePresets {
             return Err(format!("Theme file not found: {}", theme_path.display()).into());
         }
 
-        let content = std::fs::read_to_string(&theme_path)?;
-        let mut config: Config = toml::from_str(&content)?;
+        // Theme files of older versions are upgraded in memory
+        let (mut config, _) = migration::load_file(&theme_path, false)?;
 
         // Ensure the theme field matches the requested theme
         config.theme = theme_name.to_string();
@@ -50,10 +56,10 @@ impl ThemePresets {
         Ok(config)
     }
 
-    /// Get the themes directory path (~/.claude/micusubcodeline/themes/)
+    /// Get the themes directory path (`themes/` in the config directory)
     fn get_themes_path() -> std::path::PathBuf {
-        if let Some(home) = dirs::home_dir() {
-            home.join(".claude").join("micusubcodeline").join("themes")
+        if let Some(config_dir) = crate::config::paths::config_dir() {
+            config_dir.join("themes")
         } else {
             std::path::PathBuf::from(".claude/micusubcodeline/themes")
         }
@@ -124,6 +130,7 @@ impl ThemePresets {
 
     pub fn get_cometix() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: " | ".to_string(),
@@ -145,6 +152,7 @@ impl ThemePresets {
 
     pub fn get_default() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::Plain,
                 separator: " | ".to_string(),
@@ -166,6 +174,7 @@ impl ThemePresets {
 
     pub fn get_minimal() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::Plain,
                 separator: " │ ".to_string(),
@@ -186,6 +195,7 @@ impl ThemePresets {
 
     pub fn get_gruvbox() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: " | ".to_string(),
@@ -206,6 +216,7 @@ impl ThemePresets {
 
     pub fn get_nord() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
@@ -226,6 +237,7 @@ impl ThemePresets {
 
     pub fn get_powerline_dark() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
@@ -246,6 +258,7 @@ impl ThemePresets {
 
     pub fn get_powerline_light() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
@@ -266,6 +279,7 @@ impl ThemePresets {
 
     pub fn get_powerline_rose_pine() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
@@ -286,6 +300,7 @@ impl ThemePresets {
 
     pub fn get_powerline_tokyo_night() -> Config {
         Config {
+            version: CONFIG_VERSION,
             style: StyleConfig {
                 mode: StyleMode::NerdFont,
                 separator: "".to_string(),
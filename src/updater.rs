@@ -77,10 +77,7 @@ impl UpdateState {
     pub fn load() -> Self {
         #[cfg(feature = "self-update")]
         {
-            let config_dir = dirs::home_dir()
-                .unwrap_or_default()
-                .join(".claude")
-                .join("micusubcodeline");
+            let config_dir = crate::config::paths::cache_dir().unwrap_or_default();
 
             let state_file = config_dir.join(".update_state.json");
 
@@ -192,10 +189,7 @@ impl UpdateState {
     pub fn save(&self) -> Result<(), std::io::Error> {
         #[cfg(feature = "self-update")]
         {
-            let config_dir = dirs::home_dir()
-                .unwrap_or_default()
-                .join(".claude")
-                .join("micusubcodeline");
+            let config_dir = crate::config::paths::cache_dir().unwrap_or_default();
 
             std::fs::create_dir_all(&config_dir)?;
             let state_file = config_dir.join(".update_state.json");
@@ -1,5 +1,34 @@
+use crate::config::paths;
 use serde::{Deserialize, Serialize};
+use std::fs;
 use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Token endpoint used to refresh Claude Code's OAuth credentials
+pub const DEFAULT_TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
+
+/// Environment variable overriding the token endpoint (e.g. a local stand-in
+/// for testing); takes precedence over the Usage segment's `oauth_token_url`
+const TOKEN_URL_ENV: &str = "MICUSUBCODELINE_OAUTH_TOKEN_URL";
+
+/// Claude Code's OAuth client id, sent with refresh requests
+const OAUTH_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+
+const KEYCHAIN_SERVICE: &str = "Claude Code-credentials";
+
+/// Tokens this close to `expiresAt` count as expired
+const EXPIRY_SKEW_MS: u64 = 30_000;
+
+/// Held while a refresh is in flight so concurrent statusline processes
+/// don't each rotate the refresh token; older locks are considered stale
+const REFRESH_LOCK_FILE: &str = ".oauth_refresh.lock";
+const REFRESH_LOCK_SECS: u64 = 30;
+
+const REFRESH_TIMEOUT_SECS: u64 = 5;
+
+/// Lifetime assumed when the token endpoint omits `expires_in`, kept short
+/// so an unknown expiry is checked again soon rather than never
+const DEFAULT_TOKEN_LIFETIME_SECS: u64 = 60 * 60;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct OAuthCredentials {
@@ -14,21 +43,227 @@ struct OAuthCredentials {
     subscription_type: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct CredentialsFile {
-    #[serde(rename = "claudeAiOauth")]
-    claude_ai_oauth: Option<OAuthCredentials>,
+impl OAuthCredentials {
+    /// Milliseconds until `expiresAt`; `None` if the expiry is unknown
+    fn expires_in_ms(&self) -> Option<i64> {
+        self.expires_at
+            .map(|expires_at| expires_at as i64 - now_ms() as i64)
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_in_ms()
+            .is_some_and(|left| left < EXPIRY_SKEW_MS as i64)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    scope: Option<String>,
 }
 
+/// Where the credentials were read from, and so where a refresh is written
+#[derive(Debug, Clone, PartialEq)]
+pub enum CredentialSource {
+    File(PathBuf),
+    /// The macOS login keychain
+    Keychain,
+}
+
+/// The credentials document as stored. Kept as JSON so fields this tool
+/// doesn't know about survive a write-back.
+struct StoredCredentials {
+    source: CredentialSource,
+    document: serde_json::Value,
+    oauth: OAuthCredentials,
+}
+
+/// State of the stored OAuth token, for diagnostics
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenStatus {
+    /// No Claude Code OAuth credentials found
+    Missing,
+    /// Usable; seconds until expiry when known
+    Valid {
+        source: CredentialSource,
+        expires_in_secs: Option<u64>,
+    },
+    /// Past `expiresAt`
+    Expired {
+        source: CredentialSource,
+        refreshable: bool,
+    },
+}
+
+/// Current token status, without refreshing
+pub fn token_status() -> TokenStatus {
+    let Some(stored) = read_credentials() else {
+        return TokenStatus::Missing;
+    };
+    if stored.oauth.is_expired() {
+        TokenStatus::Expired {
+            source: stored.source,
+            refreshable: stored.oauth.refresh_token.is_some(),
+        }
+    } else {
+        TokenStatus::Valid {
+            source: stored.source,
+            expires_in_secs: stored
+                .oauth
+                .expires_in_ms()
+                .map(|left| left.max(0) as u64 / 1000),
+        }
+    }
+}
+
+/// Token endpoint to use: the environment override, then `configured`, then
+/// the default
+pub fn token_url(configured: Option<&str>) -> String {
+    std::env::var(TOKEN_URL_ENV)
+        .ok()
+        .filter(|url| !url.is_empty())
+        .or_else(|| configured.map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_TOKEN_URL.to_string())
+}
+
+/// A usable access token; an expired one is refreshed in the background
+/// through the default token endpoint
 pub fn get_oauth_token() -> Option<String> {
+    get_oauth_token_with(&token_url(None))
+}
+
+/// A usable access token. `None` if there are no credentials or the token
+/// has expired; an expired token is then refreshed through `token_url` by a
+/// background process, so a later render picks up the new one.
+pub fn get_oauth_token_with(token_url: &str) -> Option<String> {
+    let stored = read_credentials()?;
+    if !stored.oauth.is_expired() {
+        return Some(stored.oauth.access_token);
+    }
+    if stored.oauth.refresh_token.is_some() {
+        spawn_background_refresh(token_url);
+    }
+    None
+}
+
+/// Exchange the stored refresh token for a new access token and write the
+/// result back where it was read from. Returns the new access token.
+pub fn refresh_oauth_token(token_url: &str) -> Result<String, String> {
+    let _lock = RefreshLock::acquire().ok_or("another refresh is in progress")?;
+    refresh_locked(token_url)
+}
+
+/// Body of the `--refresh-oauth` process started by
+/// `spawn_background_refresh`, which took the lock on its behalf
+pub fn run_background_refresh(token_url: &str) -> Result<String, String> {
+    let _lock = RefreshLock::adopt().ok_or("no cache directory")?;
+    refresh_locked(token_url)
+}
+
+/// Start `--refresh-oauth` as a detached process holding the refresh lock.
+/// A refresh inside the statusline could be cut short when the process
+/// exits after printing, losing a rotated refresh token; the detached
+/// process always runs to completion.
+fn spawn_background_refresh(token_url: &str) {
+    let Some(lock) = RefreshLock::acquire() else {
+        return;
+    };
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+
+    let mut command = std::process::Command::new(exe);
+    // The background process must use the same config directory
+    if let Some(config_dir) = paths::config_dir_override() {
+        command.arg("--config-dir").arg(config_dir);
+    }
+    command
+        .args(["--refresh-oauth", "--oauth-token-url", token_url])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW: don't flash a console window
+        command.creation_flags(0x0800_0000);
+    }
+
+    if command.spawn().is_ok() {
+        // The child releases the lock when it is done
+        lock.hand_over();
+    }
+}
+
+fn refresh_locked(token_url: &str) -> Result<String, String> {
+    // Re-read under the lock: Claude Code or another statusline process may
+    // have refreshed since the caller looked
+    let mut stored = read_credentials().ok_or("no OAuth credentials found")?;
+    if !stored.oauth.is_expired() {
+        return Ok(stored.oauth.access_token);
+    }
+    let refresh_token = stored
+        .oauth
+        .refresh_token
+        .clone()
+        .ok_or("credentials have no refreshToken")?;
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(REFRESH_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .post(token_url)
+        .json(&serde_json::json!({
+            "grant_type": "refresh_token",
+            "refresh_token": refresh_token,
+            "client_id": OAUTH_CLIENT_ID,
+        }))
+        .send()
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("token endpoint returned {}", response.status()));
+    }
+    let token: TokenResponse = response.json().map_err(|e| e.to_string())?;
+
+    // The server may rotate the refresh token; keep the old one otherwise
+    stored.oauth.access_token = token.access_token;
+    if let Some(refresh_token) = token.refresh_token {
+        stored.oauth.refresh_token = Some(refresh_token);
+    }
+    let expires_in = token.expires_in.unwrap_or(DEFAULT_TOKEN_LIFETIME_SECS);
+    stored.oauth.expires_at = Some(now_ms() + expires_in * 1000);
+    if let Some(scope) = token.scope {
+        stored.oauth.scopes = Some(scope.split_whitespace().map(str::to_string).collect());
+    }
+
+    write_credentials(&mut stored)?;
+    Ok(stored.oauth.access_token)
+}
+
+fn read_credentials() -> Option<StoredCredentials> {
     if cfg!(target_os = "macos") {
-        get_oauth_token_macos()
-    } else {
-        get_oauth_token_file()
+        if let Some(stored) = read_keychain() {
+            return Some(stored);
+        }
     }
+    read_credentials_file()
+}
+
+fn parse_credentials(source: CredentialSource, content: &str) -> Option<StoredCredentials> {
+    let document: serde_json::Value = serde_json::from_str(content).ok()?;
+    let oauth = serde_json::from_value(document.get("claudeAiOauth")?.clone()).ok()?;
+    Some(StoredCredentials {
+        source,
+        document,
+        oauth,
+    })
 }
 
-fn get_oauth_token_macos() -> Option<String> {
+fn read_keychain() -> Option<StoredCredentials> {
     use std::process::Command;
 
     let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
@@ -40,41 +275,333 @@ fn get_oauth_token_macos() -> Option<String> {
             &user,
             "-w",
             "-s",
-            "Claude Code-credentials",
+            KEYCHAIN_SERVICE,
         ])
-        .output();
-
-    match output {
-        Ok(output) if output.status.success() => {
-            let json_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !json_str.is_empty() {
-                if let Ok(creds_file) = serde_json::from_str::<CredentialsFile>(&json_str) {
-                    return creds_file.claude_ai_oauth.map(|oauth| oauth.access_token);
-                }
-            }
-            None
-        }
-        _ => {
-            // Fallback to file-based credentials
-            get_oauth_token_file()
-        }
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
     }
+    let json_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    parse_credentials(CredentialSource::Keychain, &json_str)
 }
 
-fn get_oauth_token_file() -> Option<String> {
+fn read_credentials_file() -> Option<StoredCredentials> {
     let credentials_path = get_credentials_path()?;
 
     if !credentials_path.exists() {
         return None;
     }
 
-    let content = std::fs::read_to_string(&credentials_path).ok()?;
-    let creds_file: CredentialsFile = serde_json::from_str(&content).ok()?;
+    let content = fs::read_to_string(&credentials_path).ok()?;
+    parse_credentials(CredentialSource::File(credentials_path), &content)
+}
+
+/// Store refreshed credentials, changing only the OAuth token fields
+fn write_credentials(stored: &mut StoredCredentials) -> Result<(), String> {
+    let oauth = stored
+        .document
+        .get_mut("claudeAiOauth")
+        .and_then(|v| v.as_object_mut())
+        .ok_or("credentials have no claudeAiOauth object")?;
+    oauth.insert(
+        "accessToken".to_string(),
+        stored.oauth.access_token.clone().into(),
+    );
+    oauth.insert(
+        "refreshToken".to_string(),
+        stored.oauth.refresh_token.clone().into(),
+    );
+    oauth.insert("expiresAt".to_string(), stored.oauth.expires_at.into());
+    if let Some(scopes) = &stored.oauth.scopes {
+        oauth.insert("scopes".to_string(), scopes.clone().into());
+    }
+
+    let json = serde_json::to_string(&stored.document).map_err(|e| e.to_string())?;
+    match &stored.source {
+        CredentialSource::File(path) => write_file_atomic(path, &json),
+        CredentialSource::Keychain => write_keychain(&json),
+    }
+}
+
+/// Replace the file through a temp file + rename in the same directory, so
+/// Claude Code never reads a partial file. The original permissions are kept.
+fn write_file_atomic(path: &PathBuf, content: &str) -> Result<(), String> {
+    let permissions = fs::metadata(path).map_err(|e| e.to_string())?.permissions();
+    let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+
+    let result = fs::write(&tmp_path, content)
+        .and_then(|_| fs::set_permissions(&tmp_path, permissions))
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result.map_err(|e| e.to_string())
+}
+
+/// Update the keychain item through `security -i`, which reads the command
+/// from stdin, so the credentials never show up in the process list
+fn write_keychain(content: &str) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
+    let hex: String = content.bytes().map(|b| format!("{:02x}", b)).collect();
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let line = format!(
+        "add-generic-password -U -a {} -s {} -X {}\n",
+        quote(&user),
+        quote(KEYCHAIN_SERVICE),
+        hex
+    );
 
-    creds_file.claude_ai_oauth.map(|oauth| oauth.access_token)
+    let mut child = Command::new("security")
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let written = child
+        .stdin
+        .take()
+        .ok_or("security has no stdin")
+        .and_then(|mut stdin| stdin.write_all(line.as_bytes()).map_err(|_| "write failed"));
+    // Closing stdin ends the interactive session
+    let status = child.wait().map_err(|e| e.to_string())?;
+    written?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("security exited with {}", status))
+    }
 }
 
 fn get_credentials_path() -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    Some(home.join(".claude").join(".credentials.json"))
+    Some(paths::claude_dir()?.join(".credentials.json"))
+}
+
+/// Exclusive refresh lock, released on drop
+struct RefreshLock(PathBuf);
+
+impl RefreshLock {
+    fn path() -> Option<PathBuf> {
+        Some(paths::cache_dir()?.join(REFRESH_LOCK_FILE))
+    }
+
+    fn acquire() -> Option<Self> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        let stale = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|elapsed| elapsed.as_secs() >= REFRESH_LOCK_SECS);
+        if stale {
+            let _ = fs::remove_file(&path);
+        }
+
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .ok()
+            .map(|_| Self(path))
+    }
+
+    /// Take over a lock acquired by the parent process
+    fn adopt() -> Option<Self> {
+        Self::path().map(Self)
+    }
+
+    /// Keep the lock file for a child process to `adopt`
+    fn hand_over(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for RefreshLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Not on macOS, where credentials are read from the login keychain first
+#[cfg(all(test, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use std::thread::JoinHandle;
+
+    /// The tests share the process environment
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// A fresh `CLAUDE_CONFIG_DIR` holding expired credentials
+    fn setup(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "micusubcodeline-oauth-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("micusubcodeline")).unwrap();
+        fs::write(
+            dir.join(".credentials.json"),
+            serde_json::json!({
+                "claudeAiOauth": {
+                    "accessToken": "old-access",
+                    "refreshToken": "old-refresh",
+                    "expiresAt": 1_000,
+                    "scopes": ["user:inference"],
+                    "subscriptionType": "max"
+                },
+                "otherField": true
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::env::set_var("CLAUDE_CONFIG_DIR", &dir);
+        dir
+    }
+
+    /// Serve one request with `status` and `body`; the handle returns the
+    /// request body
+    fn token_endpoint(status: &str, body: serde_json::Value) -> JoinHandle<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        std::env::set_var(
+            TOKEN_URL_ENV,
+            format!("http://{}/v1/oauth/token", listener.local_addr().unwrap()),
+        );
+        let status = status.to_string();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request = vec![0; content_length];
+            reader.read_exact(&mut request).unwrap();
+
+            let body = body.to_string();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            String::from_utf8(request).unwrap()
+        })
+    }
+
+    fn stored_oauth(dir: &Path) -> serde_json::Value {
+        let content = fs::read_to_string(dir.join(".credentials.json")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn refresh_writes_new_token_and_keeps_other_fields() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = setup("success");
+        let server = token_endpoint(
+            "200 OK",
+            serde_json::json!({ "access_token": "new-access", "scope": "user:inference user:profile" }),
+        );
+
+        let token = refresh_oauth_token(&token_url(None)).unwrap();
+        assert_eq!(token, "new-access");
+
+        let request: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(request["grant_type"], "refresh_token");
+        assert_eq!(request["refresh_token"], "old-refresh");
+        assert_eq!(request["client_id"], OAUTH_CLIENT_ID);
+
+        let stored = stored_oauth(&dir);
+        let oauth = &stored["claudeAiOauth"];
+        assert_eq!(oauth["accessToken"], "new-access");
+        // No refresh token in the response: the old one stays
+        assert_eq!(oauth["refreshToken"], "old-refresh");
+        assert_eq!(
+            oauth["scopes"],
+            serde_json::json!(["user:inference", "user:profile"])
+        );
+        assert_eq!(oauth["subscriptionType"], "max");
+        assert_eq!(stored["otherField"], true);
+
+        // No expires_in: the default lifetime applies
+        let expires_in = oauth["expiresAt"].as_u64().unwrap() - now_ms();
+        let default_ms = DEFAULT_TOKEN_LIFETIME_SECS * 1000;
+        assert!(expires_in <= default_ms && expires_in > default_ms - 60_000);
+
+        assert!(!dir.join("micusubcodeline").join(REFRESH_LOCK_FILE).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn refresh_stores_rotated_refresh_token() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = setup("rotated");
+        let server = token_endpoint(
+            "200 OK",
+            serde_json::json!({
+                "access_token": "new-access",
+                "refresh_token": "new-refresh",
+                "expires_in": 28_800
+            }),
+        );
+
+        refresh_oauth_token(&token_url(None)).unwrap();
+        server.join().unwrap();
+
+        let stored = stored_oauth(&dir);
+        let oauth = &stored["claudeAiOauth"];
+        assert_eq!(oauth["refreshToken"], "new-refresh");
+        let expires_in = oauth["expiresAt"].as_u64().unwrap() - now_ms();
+        assert!(expires_in > 28_000 * 1000);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn refresh_error_leaves_credentials_untouched() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = setup("error");
+        let before = fs::read_to_string(dir.join(".credentials.json")).unwrap();
+        let server = token_endpoint(
+            "400 Bad Request",
+            serde_json::json!({ "error": "invalid_grant" }),
+        );
+
+        let error = refresh_oauth_token(&token_url(None)).unwrap_err();
+        assert!(error.contains("400"), "{}", error);
+        server.join().unwrap();
+
+        let after = fs::read_to_string(dir.join(".credentials.json")).unwrap();
+        assert_eq!(before, after);
+        assert!(!dir.join("micusubcodeline").join(REFRESH_LOCK_FILE).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,7 @@
+/// 64-bit FNV-1a, used for cache file names and fingerprints because,
+/// unlike `DefaultHasher`, its output is stable across Rust releases
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
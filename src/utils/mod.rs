@@ -1,5 +1,6 @@
 pub mod claude_code_patcher;
 pub mod credentials;
+pub mod hash;
 pub mod setup_wizard;
 pub mod subscription_api;
 
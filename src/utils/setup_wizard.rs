@@ -19,6 +19,13 @@ impl SetupWizard {
             Ok(crate::config::InitResult::Created(path)) => {
                 println!("✅ 配置文件已创建: {}", path.display());
             }
+            Ok(crate::config::InitResult::Imported { path, from }) => {
+                println!(
+                    "✅ 配置文件已创建: {}（导入自 {}）",
+                    path.display(),
+                    from.display()
+                );
+            }
             Ok(crate::config::InitResult::AlreadyExists(path)) => {
                 println!("✅ 配置文件已存在: {}", path.display());
             }
@@ -74,9 +81,7 @@ impl SetupWizard {
     /// 检测 API Key 的实际来源
     fn detect_key_source() -> &'static str {
         // 按优先级依次检查
-        if let Some(home) = dirs::home_dir() {
-            let claude_dir = home.join(".claude");
-
+        if let Some(claude_dir) = crate::config::paths::claude_dir() {
             let local_settings = claude_dir.join("settings.local.json");
             if Self::has_api_key_in_settings(&local_settings) {
                 return "settings.local.json";
@@ -1,17 +1,37 @@
 use crate::config::types::Subscription;
-use serde::Deserialize;
+use crate::config::{paths, Config, SegmentId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const CONFIG_FILE: &str = "subscription_config.txt";
+/// 缓存与刷新锁按 API Key 与端点列表的指纹分文件存放，
+/// 不同项目配置的端点互不覆盖
+const CACHE_FILE_PREFIX: &str = ".subscription_cache_";
+const REFRESH_LOCK_FILE_PREFIX: &str = ".subscription_refresh_";
+
+/// 后台刷新进程的最长存活时间，超过后锁文件视为失效
+const REFRESH_LOCK_SECS: u64 = 30;
+
+/// 默认缓存有效期（秒）
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+/// 默认 Sub2API 地址，所有自定义端点失败后的最终回退
 const BASE_URL: &str = "https://sub.openclaudecode.cn";
 
+/// 可覆盖 Sub2API 地址的环境变量（优先于 ANTHROPIC_BASE_URL）
+const BASE_URL_ENV: &str = "SUB2API_BASE_URL";
+
 /// Claude Code settings 文件读取优先级（从高到低）
 const SETTINGS_FILES: &[&str] = &["settings.local.json", "settings.json"];
 
 /// settings.json 中可能存放 API Key 的字段名（按优先级）
 const API_KEY_FIELDS: &[&str] = &["ANTHROPIC_API_KEY", "ANTHROPIC_AUTH_TOKEN"];
 
+/// settings.json 中存放 API 地址的字段名
+const BASE_URL_FIELDS: &[&str] = &["ANTHROPIC_BASE_URL"];
+
 /// GET /v1/usage 统一响应结构
 #[derive(Deserialize)]
 struct UsageResponse {
@@ -42,8 +62,28 @@ struct UsagePeriod {
     cost_usd: Option<f64>,
 }
 
+/// 订阅信息磁盘缓存（与 .api_usage_cache.json 位于同一目录）
+#[derive(Serialize, Deserialize)]
+struct SubscriptionCache {
+    /// API Key 与端点列表的指纹，切换 Key 或端点后旧缓存自动失效
+    key_fingerprint: String,
+    subscription: Subscription,
+    cached_at: u64,
+}
+
+/// 从缓存读取的订阅信息
+pub struct CachedSubscription {
+    pub subscription: Subscription,
+    /// 缓存已过期，正在后台刷新
+    pub stale: bool,
+    /// 缓存数据的年龄（秒）
+    pub age_secs: u64,
+}
+
 pub struct SubscriptionApi {
     api_key: String,
+    /// 按顺序尝试的 Sub2API 地址
+    endpoints: Vec<String>,
 }
 
 impl SubscriptionApi {
@@ -51,42 +91,126 @@ impl SubscriptionApi {
     /// 优先级：settings.local.json > settings.json > 环境变量 > subscription_config.txt
     pub fn load() -> Option<Self> {
         // 1. 尝试从 Claude Code settings 文件读取
-        if let Some(api_key) = Self::read_key_from_claude_settings() {
-            return Some(Self { api_key });
+        // 2. 尝试从环境变量读取
+        // 3. 回退到 subscription_config.txt
+        let api_key = Self::read_settings_env(API_KEY_FIELDS)
+            .or_else(|| Self::read_process_env(API_KEY_FIELDS))
+            .or_else(Self::read_key_from_config_file)?;
+
+        Some(Self {
+            api_key,
+            endpoints: Self::resolve_endpoints(None, &[]),
+        })
+    }
+
+    /// 加载订阅API配置，并应用 config.toml 中 subscription segment 的端点选项
+    pub fn load_from_config(config: &Config) -> Option<Self> {
+        let api = Self::load()?;
+        match config
+            .segments
+            .iter()
+            .find(|s| s.id == SegmentId::Subscription)
+        {
+            Some(segment) => {
+                let (base_url, fallback_urls) = Self::endpoint_options(&segment.options);
+                Some(api.with_endpoints(base_url.as_deref(), &fallback_urls))
+            }
+            None => Some(api),
         }
+    }
 
-        // 2. 尝试从环境变量读取
-        if let Some(api_key) = Self::read_key_from_env() {
-            return Some(Self { api_key });
+    /// 从 segment 选项中解析 `api_base_url` 与 `fallback_urls`
+    pub fn endpoint_options(
+        options: &HashMap<String, serde_json::Value>,
+    ) -> (Option<String>, Vec<String>) {
+        let base_url = options
+            .get("api_base_url")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let fallback_urls = options
+            .get("fallback_urls")
+            .and_then(|v| v.as_array())
+            .map(|urls| {
+                urls.iter()
+                    .filter_map(|u| u.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        (base_url, fallback_urls)
+    }
+
+    /// 使用指定的主地址与备用地址列表
+    pub fn with_endpoints(mut self, base_url: Option<&str>, fallback_urls: &[String]) -> Self {
+        self.endpoints = Self::resolve_endpoints(base_url, fallback_urls);
+        self
+    }
+
+    /// 直接使用已解析好的端点列表（后台刷新进程沿用状态栏进程的端点）
+    pub fn with_endpoint_list(mut self, endpoints: Vec<String>) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// 按顺序尝试的端点列表
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    /// 解析端点列表
+    ///
+    /// 主地址优先级：segment 选项 api_base_url、settings 中 ANTHROPIC_BASE_URL、
+    /// 环境变量 SUB2API_BASE_URL、环境变量 ANTHROPIC_BASE_URL；其后为 fallback_urls。
+    /// 默认地址仅在主地址与备用地址都未配置时使用，避免自建服务故障时把 Key 发给第三方
+    fn resolve_endpoints(base_url: Option<&str>, fallback_urls: &[String]) -> Vec<String> {
+        let primary = base_url
+            .map(|s| s.to_string())
+            .or_else(|| Self::read_settings_env(BASE_URL_FIELDS))
+            .or_else(|| Self::read_process_env(&[BASE_URL_ENV]))
+            .or_else(|| Self::read_process_env(BASE_URL_FIELDS));
+
+        let mut candidates: Vec<String> = primary
+            .into_iter()
+            .chain(fallback_urls.iter().cloned())
+            .filter(|url| !url.trim().is_empty())
+            .collect();
+        if candidates.is_empty() {
+            candidates.push(BASE_URL.to_string());
         }
 
-        // 3. 回退到 subscription_config.txt
-        Self::read_key_from_config_file()
+        let mut endpoints: Vec<String> = Vec::new();
+
+        for candidate in candidates {
+            let url = candidate.trim().trim_end_matches('/').to_string();
+            if !url.is_empty() && !endpoints.contains(&url) {
+                endpoints.push(url);
+            }
+        }
+
+        endpoints
     }
 
-    /// 从 Claude Code settings 文件中读取 ANTHROPIC_API_KEY
-    fn read_key_from_claude_settings() -> Option<String> {
-        let home = dirs::home_dir()?;
-        let claude_dir = home.join(".claude");
+    /// 从 Claude Code settings 文件的 env 中读取第一个非空字段
+    fn read_settings_env(fields: &[&str]) -> Option<String> {
+        let claude_dir = paths::claude_dir()?;
 
         for filename in SETTINGS_FILES {
             let path = claude_dir.join(filename);
-            if let Some(key) = Self::extract_api_key_from_settings(&path) {
-                return Some(key);
+            if let Some(value) = Self::extract_env_from_settings(&path, fields) {
+                return Some(value);
             }
         }
 
         None
     }
 
-    /// 从单个 settings JSON 文件中提取 API Key
-    fn extract_api_key_from_settings(path: &PathBuf) -> Option<String> {
+    /// 从单个 settings JSON 文件的 env 中提取字段
+    fn extract_env_from_settings(path: &PathBuf, fields: &[&str]) -> Option<String> {
         let content = fs::read_to_string(path).ok()?;
         let settings: serde_json::Value = serde_json::from_str(&content).ok()?;
 
         let env = settings.get("env")?;
 
-        for field in API_KEY_FIELDS {
+        for field in fields {
             if let Some(key) = env
                 .get(*field)
                 .and_then(|v| v.as_str())
@@ -100,9 +224,9 @@ impl SubscriptionApi {
         None
     }
 
-    /// 从环境变量读取 API Key
-    fn read_key_from_env() -> Option<String> {
-        for field in API_KEY_FIELDS {
+    /// 从进程环境变量读取第一个非空字段
+    fn read_process_env(fields: &[&str]) -> Option<String> {
+        for field in fields {
             if let Some(key) = std::env::var(field)
                 .ok()
                 .map(|s| s.trim().to_string())
@@ -115,7 +239,7 @@ impl SubscriptionApi {
     }
 
     /// 从 subscription_config.txt 读取（原有逻辑）
-    fn read_key_from_config_file() -> Option<Self> {
+    fn read_key_from_config_file() -> Option<String> {
         let config_path = Self::get_config_path()?;
 
         if !config_path.exists() {
@@ -136,21 +260,195 @@ impl SubscriptionApi {
             return None;
         }
 
-        Some(Self { api_key })
+        Some(api_key)
     }
 
-    /// 获取配置文件路径（~/.claude/micusubcodeline/subscription_config.txt）
+    /// 获取配置文件路径（配置目录下的 subscription_config.txt，见 `config::paths`）
     fn get_config_path() -> Option<PathBuf> {
-        dirs::home_dir().map(|home| {
-            home.join(".claude")
-                .join("micusubcodeline")
-                .join(CONFIG_FILE)
+        paths::config_dir().map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    /// 获取缓存目录下的文件路径
+    fn get_data_path(file_name: &str) -> Option<PathBuf> {
+        paths::cache_dir().map(|dir| dir.join(file_name))
+    }
+
+    /// 获取订阅信息（带磁盘缓存，stale-while-revalidate）
+    ///
+    /// - 缓存在 `ttl_secs` 内：直接返回缓存
+    /// - 缓存已过期：立即返回旧数据，并启动后台进程刷新缓存
+    /// - 无缓存：同步请求 API 并写入缓存
+    pub fn get_subscription_info_cached(&self, ttl_secs: u64) -> Option<CachedSubscription> {
+        let Some(cache) = self.load_cache() else {
+            let subscription = self.refresh_cache()?;
+            return Some(CachedSubscription {
+                subscription,
+                stale: false,
+                age_secs: 0,
+            });
+        };
+
+        let age_secs = now_secs().saturating_sub(cache.cached_at);
+        let stale = age_secs >= ttl_secs;
+        if stale {
+            self.spawn_background_refresh();
+        }
+
+        // 按缓存年龄修正刷新倒计时
+        let mut subscription = cache.subscription;
+        if let Some(reset) = subscription.resets_in_seconds.as_mut() {
+            *reset = reset.saturating_sub(age_secs as i64);
+        }
+
+        Some(CachedSubscription {
+            subscription,
+            stale,
+            age_secs,
         })
     }
 
+    /// 请求 API 并写入缓存（后台刷新进程与首次请求使用）
+    pub fn refresh_cache(&self) -> Option<Subscription> {
+        let subscription = self.get_subscription_info()?;
+        let cache = SubscriptionCache {
+            key_fingerprint: self.key_fingerprint(),
+            subscription: subscription.clone(),
+            cached_at: now_secs(),
+        };
+
+        if let Some(cache_path) = self.cache_path() {
+            if let Some(parent) = cache_path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            // 先写临时文件再重命名，读取方不会看到写了一半的缓存
+            if let Ok(json) = serde_json::to_string_pretty(&cache) {
+                let tmp_path = cache_path.with_extension(format!("json.{}", std::process::id()));
+                if fs::write(&tmp_path, json).is_ok() && fs::rename(&tmp_path, &cache_path).is_err()
+                {
+                    let _ = fs::remove_file(&tmp_path);
+                }
+            }
+        }
+
+        Some(subscription)
+    }
+
+    /// 后台刷新进程结束时释放锁
+    pub fn release_refresh_lock(&self) {
+        if let Some(lock_path) = self.lock_path() {
+            let _ = fs::remove_file(lock_path);
+        }
+    }
+
+    fn cache_path(&self) -> Option<PathBuf> {
+        Self::get_data_path(&format!(
+            "{}{}.json",
+            CACHE_FILE_PREFIX,
+            self.key_fingerprint()
+        ))
+    }
+
+    fn lock_path(&self) -> Option<PathBuf> {
+        Self::get_data_path(&format!(
+            "{}{}.lock",
+            REFRESH_LOCK_FILE_PREFIX,
+            self.key_fingerprint()
+        ))
+    }
+
+    fn load_cache(&self) -> Option<SubscriptionCache> {
+        let cache_path = self.cache_path()?;
+        let content = fs::read_to_string(cache_path).ok()?;
+        let cache: SubscriptionCache = serde_json::from_str(&content).ok()?;
+        if cache.key_fingerprint != self.key_fingerprint() {
+            return None;
+        }
+        Some(cache)
+    }
+
+    /// API Key 与端点列表的指纹（FNV-1a，跨 Rust 版本稳定）
+    fn key_fingerprint(&self) -> String {
+        let mut input = self.api_key.clone();
+        for endpoint in &self.endpoints {
+            input.push('\n');
+            input.push_str(endpoint);
+        }
+        format!("{:016x}", super::hash::fnv1a_64(input.as_bytes()))
+    }
+
+    /// 启动独立的后台进程刷新缓存，避免阻塞状态栏
+    fn spawn_background_refresh(&self) {
+        let Some(lock_path) = self.lock_path() else {
+            return;
+        };
+
+        // 清理失效的锁，再以 create_new 原子地获取锁；已有刷新进程在运行则跳过
+        let stale = fs::metadata(&lock_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|elapsed| elapsed.as_secs() >= REFRESH_LOCK_SECS);
+        if stale {
+            let _ = fs::remove_file(&lock_path);
+        }
+        let Ok(mut lock) = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        else {
+            return;
+        };
+        let _ = std::io::Write::write_all(&mut lock, std::process::id().to_string().as_bytes());
+
+        let Ok(exe) = std::env::current_exe() else {
+            self.release_refresh_lock();
+            return;
+        };
+
+        let mut command = std::process::Command::new(exe);
+        // 后台进程需沿用同一配置目录
+        if let Some(config_dir) = paths::config_dir_override() {
+            command.arg("--config-dir").arg(config_dir);
+        }
+        command.arg("--refresh-subscription");
+        // 传入本进程解析出的端点（可能来自项目配置），保证写入同一份缓存
+        for endpoint in &self.endpoints {
+            command.arg("--subscription-endpoint").arg(endpoint);
+        }
+        command
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            // CREATE_NO_WINDOW：避免弹出控制台窗口
+            command.creation_flags(0x0800_0000);
+        }
+
+        if command.spawn().is_err() {
+            self.release_refresh_lock();
+        }
+    }
+
     /// 获取订阅信息（通过 GET /v1/usage 统一端点）
     pub fn get_subscription_info(&self) -> Option<Subscription> {
-        let url = format!("{}/v1/usage", BASE_URL);
+        self.get_subscription_info_with_endpoint()
+            .map(|(subscription, _)| subscription)
+    }
+
+    /// 按顺序尝试各端点，返回订阅信息及成功响应的端点
+    pub fn get_subscription_info_with_endpoint(&self) -> Option<(Subscription, String)> {
+        self.endpoints.iter().find_map(|endpoint| {
+            self.fetch_from(endpoint)
+                .map(|subscription| (subscription, endpoint.clone()))
+        })
+    }
+
+    /// 从单个端点获取订阅信息
+    fn fetch_from(&self, endpoint: &str) -> Option<Subscription> {
+        let url = format!("{}/v1/usage", endpoint);
         let client = reqwest::blocking::Client::builder()
             .timeout(std::time::Duration::from_secs(5))
             .build()
@@ -200,3 +498,10 @@ impl SubscriptionApi {
         }
     }
 }
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}